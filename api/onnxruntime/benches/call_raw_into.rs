@@ -0,0 +1,92 @@
+//! Measures the allocations saved by writing the outputs into the given buffers.
+//!
+//! Run with `cargo bench --bench call_raw_into`.
+
+#[allow(dead_code)]
+#[path = "../src/testing.rs"]
+mod testing;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use ipis::core::ndarray;
+use ipnis_api_onnxruntime::client::IpnisClientInner;
+use ipnis_common::{tensor::Tensor, Ipnis};
+
+use crate::testing::MockIpsis;
+
+const NUM_ITERATIONS: u32 = 1000;
+
+/// The system allocator, counting the allocations to measure the allocation churn.
+struct CountingAllocator;
+
+static NUM_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static NUM_ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        NUM_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        NUM_ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the number of the allocations and the allocated bytes so far, in the whole process.
+///
+/// NOTE: the allocations of the ONNX Runtime itself are not counted, as it uses its own allocators
+fn allocations() -> (usize, usize) {
+    (
+        NUM_ALLOCATIONS.load(Ordering::Relaxed),
+        NUM_ALLOCATED_BYTES.load(Ordering::Relaxed),
+    )
+}
+
+fn main() {
+    let model_bytes = testing::relu().into_model();
+    let inputs = vec![Tensor::from((
+        "x",
+        ndarray::Array::<f32, _>::zeros((256, 2)).into_dyn(),
+    ))];
+
+    testing::block_on(async {
+        let client = IpnisClientInner::with_ipiis_client(MockIpsis::default())
+            .await
+            .unwrap();
+        let path = client.ipiis.insert(model_bytes);
+        let model = client.load_model(&path).await.unwrap();
+
+        // the fresh outputs of each call
+        let (num_allocations, num_bytes) = allocations();
+        for _ in 0..NUM_ITERATIONS {
+            client.call_raw(&model, inputs.clone()).await.unwrap();
+        }
+        let (allocated, allocated_bytes) = allocations();
+        let allocated = (allocated - num_allocations) as u32 / NUM_ITERATIONS;
+        let allocated_bytes = (allocated_bytes - num_bytes) as u32 / NUM_ITERATIONS;
+
+        // the outputs of the previous call, reused as the buffers
+        let mut buffers = client.call_raw(&model, inputs.clone()).await.unwrap();
+        let (num_allocations, num_bytes) = allocations();
+        for _ in 0..NUM_ITERATIONS {
+            buffers = client
+                .call_raw_into(&model, inputs.clone(), buffers)
+                .await
+                .unwrap();
+        }
+        let (reused, reused_bytes) = allocations();
+        let reused = (reused - num_allocations) as u32 / NUM_ITERATIONS;
+        let reused_bytes = (reused_bytes - num_bytes) as u32 / NUM_ITERATIONS;
+
+        println!("allocations per call: {allocated} ({allocated_bytes}B) of the fresh outputs");
+        println!("allocations per call: {reused} ({reused_bytes}B) into the buffers");
+    })
+}
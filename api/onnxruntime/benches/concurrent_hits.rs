@@ -0,0 +1,56 @@
+//! Measures the throughput of the cache hits, made by the concurrent readers.
+//!
+//! Run with `cargo bench --bench concurrent_hits`.
+
+#[allow(dead_code)]
+#[path = "../src/testing.rs"]
+mod testing;
+
+use std::{sync::Arc, time::Instant};
+
+use ipis::tokio;
+use ipnis_api_onnxruntime::client::IpnisClientInner;
+use ipnis_common::Ipnis;
+
+use crate::testing::MockIpsis;
+
+const NUM_READERS: usize = 8;
+const NUM_ITERATIONS: usize = 10_000;
+
+/// Returns the number of the hits per second, made by the concurrent readers.
+async fn throughput(client: &Arc<IpnisClientInner<MockIpsis>>, num_readers: usize) -> f64 {
+    let path = client.ipiis.insert(testing::relu().into_model());
+    client.load_model(&path).await.unwrap();
+
+    let instant = Instant::now();
+    let readers: Vec<_> = (0..num_readers)
+        .map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                for _ in 0..NUM_ITERATIONS {
+                    client.load_model(&path).await.unwrap();
+                }
+            })
+        })
+        .collect();
+    for reader in readers {
+        reader.await.unwrap();
+    }
+    (num_readers * NUM_ITERATIONS) as f64 / instant.elapsed().as_secs_f64()
+}
+
+fn main() {
+    testing::block_on(async {
+        let client = IpnisClientInner::with_ipiis_client(MockIpsis::default())
+            .await
+            .map(Arc::new)
+            .unwrap();
+
+        let single = throughput(&client, 1).await;
+        let shared = throughput(&client, NUM_READERS).await;
+
+        println!(
+            "cache hits: {single:.0}/s with 1 reader, {shared:.0}/s with {NUM_READERS} readers"
+        );
+    })
+}
@@ -0,0 +1,59 @@
+//! Measures the latency of handing the inferences off onto the blocking threads.
+//!
+//! Run with `cargo bench --bench spawn_blocking`.
+
+#[allow(dead_code)]
+#[path = "../src/testing.rs"]
+mod testing;
+
+use std::time::Instant;
+
+use ipis::{
+    core::{ndarray, value::array::Array},
+    tokio,
+};
+use ipnis_api_onnxruntime::client::IpnisClientInner;
+use ipnis_common::{
+    tensor::{dynamic::DynamicTensorData, Tensor},
+    Ipnis,
+};
+
+use crate::testing::MockIpsis;
+
+const NUM_ITERATIONS: u32 = 1000;
+
+fn main() {
+    let model_bytes = testing::relu().into_model();
+    let inputs = vec![Tensor {
+        name: "x".into(),
+        data: DynamicTensorData::F32(Array(
+            ndarray::array![[-1.0f32, 2.0]].into_dyn().into_shared(),
+        ))
+        .into(),
+    }];
+
+    testing::block_on(async {
+        // the bare handoff onto the blocking threads
+        let instant = Instant::now();
+        for _ in 0..NUM_ITERATIONS {
+            tokio::task::spawn_blocking(|| ()).await.unwrap();
+        }
+        let handoff = instant.elapsed() / NUM_ITERATIONS;
+
+        // the whole inferences of a tiny model, including the handoff
+        let client = IpnisClientInner::with_ipiis_client(MockIpsis::default())
+            .await
+            .unwrap();
+        let path = client.ipiis.insert(model_bytes);
+        let model = client.load_model(&path).await.unwrap();
+        client.call_raw(&model, inputs.clone()).await.unwrap();
+
+        let instant = Instant::now();
+        for _ in 0..NUM_ITERATIONS {
+            client.call_raw(&model, inputs.clone()).await.unwrap();
+        }
+        let inference = instant.elapsed() / NUM_ITERATIONS;
+
+        println!("spawn_blocking: {handoff:?} of the handoff in {inference:?} per inference");
+    })
+}
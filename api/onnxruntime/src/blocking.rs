@@ -0,0 +1,50 @@
+use ipis::{
+    core::anyhow::{bail, Result},
+    env::Infer,
+    path::Path,
+    tokio,
+};
+use ipnis_common::{model::Model, tensor::Tensor, Ipnis};
+
+/// The blocking client, driving the async one on its own runtime.
+///
+/// Note that it cannot be used (nor dropped) within an async runtime.
+pub struct IpnisClient {
+    inner: crate::client::IpnisClient,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl IpnisClient {
+    pub fn try_infer() -> Result<Self> {
+        ensure_blocking()?;
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        let inner = runtime.block_on(crate::client::IpnisClient::try_infer())?;
+        Ok(Self { inner, runtime })
+    }
+
+    pub fn load_model(&self, path: &Path) -> Result<Model> {
+        ensure_blocking()?;
+        Ok(self.runtime.block_on(self.inner.load_model(path))?)
+    }
+
+    pub fn call_raw(&self, model: &Model, inputs: Vec<Tensor>) -> Result<Vec<Tensor>> {
+        ensure_blocking()?;
+        Ok(self.runtime.block_on(self.inner.call_raw(model, inputs))?)
+    }
+
+    pub fn warmup(&self, paths: &[Path]) -> Result<()> {
+        ensure_blocking()?;
+        self.runtime.block_on(self.inner.warmup(paths))
+    }
+}
+
+/// Fails instead of panicking when called within an async runtime.
+fn ensure_blocking() -> Result<()> {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        bail!("the blocking client cannot be used within an async runtime")
+    }
+    Ok(())
+}
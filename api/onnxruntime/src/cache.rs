@@ -0,0 +1,364 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use ipis::path::Path;
+use ipnis_common::onnxruntime::session::Session;
+
+use crate::{
+    config::{ExecutionProvider, LoadOptions},
+    guard::ModelScan,
+};
+
+/// The sessions are identified by both the models and the options loaded with.
+pub(crate) type SessionKey = (Path, LoadOptions);
+
+/// A LRU cache of the compiled sessions.
+///
+/// Evicting a session only drops the cache entry; the session stays alive
+/// until the other tasks holding the `Arc` are finished.
+///
+/// The cache hits only need a shared reference, so they can be served concurrently.
+///
+/// The sessions of the pinned models are never evicted, even beyond the capacity.
+///
+/// NOTE: it is generic over the sessions, so that it can be tested without building them
+pub(crate) struct SessionCache<S = Session> {
+    capacity: usize,
+    clock: AtomicU64,
+    epoch: Instant,
+    evictions: u64,
+    entries: HashMap<SessionKey, SessionEntry<S>>,
+    pinned: HashSet<Path>,
+}
+
+struct SessionEntry<S> {
+    session: Arc<S>,
+    /// The execution provider which the session has been built with.
+    provider: ExecutionProvider,
+    /// The facts of the model, found while loading it.
+    scan: ModelScan,
+    last_used: AtomicU64,
+    /// The milliseconds since the epoch of the cache.
+    last_accessed: AtomicU64,
+}
+
+impl<S> SessionCache<S> {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            clock: Default::default(),
+            epoch: Instant::now(),
+            evictions: 0,
+            entries: Default::default(),
+            pinned: Default::default(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the number of the sessions evicted so far.
+    pub(crate) fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    pub(crate) fn get(&self, key: &SessionKey) -> Option<Arc<S>> {
+        self.entries.get(key).map(|entry| {
+            entry.last_used.store(self.tick(), Ordering::Relaxed);
+            entry.last_accessed.store(self.now(), Ordering::Relaxed);
+            entry.session.clone()
+        })
+    }
+
+    /// Returns the session which the model has been placed into with the options,
+    /// with its key and the facts of the model.
+    ///
+    /// The sessions on any device are matched if the device is not given.
+    pub(crate) fn get_placed(
+        &self,
+        path: &Path,
+        options: &LoadOptions,
+    ) -> Option<(SessionKey, Arc<S>, ModelScan)> {
+        let key = (*path, *options);
+        let key = match self.entries.contains_key(&key) {
+            true => key,
+            false => self
+                .entries
+                .keys()
+                .find(|(key, placed)| {
+                    key == path
+                        && placed.optimization_level == options.optimization_level
+                        && options.device_id.is_none()
+                })
+                .copied()?,
+        };
+        let scan = self.entries.get(&key)?.scan.clone();
+        self.get(&key).map(|session| (key, session, scan))
+    }
+
+    /// Returns the paths of the cached models, without duplicates.
+    pub(crate) fn paths(&self) -> Vec<Path> {
+        let paths: HashSet<_> = self.entries.keys().map(|(path, _)| *path).collect();
+        paths.into_iter().collect()
+    }
+
+    /// Returns whether the model is cached with any options.
+    pub(crate) fn contains(&self, path: &Path) -> bool {
+        self.entries.keys().any(|(key, _)| key == path)
+    }
+
+    /// Returns the cached models with the execution providers of their sessions.
+    pub(crate) fn providers(&self) -> Vec<(Path, ExecutionProvider)> {
+        self.entries
+            .iter()
+            .map(|((path, _), entry)| (*path, entry.provider))
+            .collect()
+    }
+
+    /// Returns the execution provider which the session has been built with.
+    pub(crate) fn provider(&self, key: &SessionKey) -> Option<ExecutionProvider> {
+        self.entries.get(key).map(|entry| entry.provider)
+    }
+
+    /// Returns the keys of the cached sessions.
+    pub(crate) fn keys(&self) -> Vec<SessionKey> {
+        self.entries.keys().copied().collect()
+    }
+
+    /// Returns the sessions of the model with any options.
+    pub(crate) fn get_all(&self, path: &Path) -> Vec<Arc<S>> {
+        self.entries
+            .iter()
+            .filter(|((key, _), _)| key == path)
+            .map(|(_, entry)| entry.session.clone())
+            .collect()
+    }
+
+    /// Stores the session, returning the cached one if the key is already loaded.
+    pub(crate) fn insert(
+        &mut self,
+        key: SessionKey,
+        session: Arc<S>,
+        provider: ExecutionProvider,
+        scan: ModelScan,
+    ) -> Arc<S> {
+        if let Some(session) = self.get(&key) {
+            return session;
+        }
+
+        // evict the least recently used sessions
+        while self.entries.len() >= self.capacity.max(1) {
+            let oldest = self
+                .entries
+                .iter()
+                .filter(|((path, _), _)| !self.pinned.contains(path))
+                .min_by_key(|(_, entry)| entry.last_used.load(Ordering::Relaxed))
+                .map(|(key, _)| *key);
+            match oldest {
+                Some(oldest) => self.entries.remove(&oldest),
+                None => break,
+            };
+            self.evictions += 1;
+        }
+
+        self.entries.insert(
+            key,
+            SessionEntry {
+                session: session.clone(),
+                provider,
+                scan,
+                last_used: self.tick().into(),
+                last_accessed: self.now().into(),
+            },
+        );
+        session
+    }
+
+    /// Exempts the sessions of the model from the eviction.
+    pub(crate) fn pin(&mut self, path: Path) {
+        self.pinned.insert(path);
+    }
+
+    /// Lets the sessions of the model be evicted, returning whether it was pinned.
+    pub(crate) fn unpin(&mut self, path: &Path) -> bool {
+        self.pinned.remove(path)
+    }
+
+    /// Drops the sessions of the model with any options, returning whether any was cached.
+    ///
+    /// The model is unpinned as well.
+    pub(crate) fn remove(&mut self, path: &Path) -> bool {
+        self.pinned.remove(path);
+        let len = self.entries.len();
+        self.entries.retain(|(key, _), _| key != path);
+        self.entries.len() != len
+    }
+
+    /// Drops the sessions which have not been used for the given duration,
+    /// returning the number of the dropped ones.
+    pub(crate) fn remove_idle(&mut self, ttl: Duration) -> usize {
+        let now = self.now();
+        let ttl = ttl.as_millis() as u64;
+
+        let len = self.entries.len();
+        let pinned = &self.pinned;
+        self.entries.retain(|(path, _), entry| {
+            pinned.contains(path)
+                || now.saturating_sub(entry.last_accessed.load(Ordering::Relaxed)) < ttl
+        });
+
+        let removed = len - self.entries.len();
+        self.evictions += removed as u64;
+        removed
+    }
+
+    fn now(&self) -> u64 {
+        self.epoch.elapsed().as_millis() as u64
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ipis::core::value::hash::Hash;
+
+    use super::*;
+
+    fn key(name: &str) -> SessionKey {
+        let path = Path {
+            value: Hash::with_str(name),
+            len: name.len() as u64,
+        };
+        (path, Default::default())
+    }
+
+    fn insert(cache: &mut SessionCache<()>, key: SessionKey) -> Arc<()> {
+        cache.insert(
+            key,
+            Arc::new(()),
+            ExecutionProvider::Cpu,
+            Default::default(),
+        )
+    }
+
+    #[test]
+    fn test_insert_cached() {
+        let mut cache = SessionCache::with_capacity(2);
+
+        let session = insert(&mut cache, key("a"));
+        assert!(Arc::ptr_eq(&insert(&mut cache, key("a")), &session));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let mut cache = SessionCache::with_capacity(2);
+        insert(&mut cache, key("a"));
+        insert(&mut cache, key("b"));
+
+        // "b" becomes the oldest one
+        assert!(cache.get(&key("a")).is_some());
+        insert(&mut cache, key("c"));
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.evictions(), 1);
+        assert!(cache.get(&key("a")).is_some());
+        assert!(cache.get(&key("b")).is_none());
+        assert!(cache.get(&key("c")).is_some());
+    }
+
+    #[test]
+    fn test_pin() {
+        let mut cache = SessionCache::with_capacity(1);
+        cache.pin(key("a").0);
+        insert(&mut cache, key("a"));
+
+        // the pinned sessions are kept beyond the capacity
+        insert(&mut cache, key("b"));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.evictions(), 0);
+
+        insert(&mut cache, key("c"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&key("a")).is_some());
+        assert!(cache.get(&key("b")).is_none());
+
+        // the unpinned sessions are evicted as usual
+        assert!(cache.unpin(&key("a").0));
+        insert(&mut cache, key("d"));
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(&key("d")).is_some());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut cache = SessionCache::with_capacity(4);
+        let (path, _) = key("a");
+        cache.pin(path);
+        insert(&mut cache, key("a"));
+        insert(
+            &mut cache,
+            (
+                path,
+                LoadOptions {
+                    device_id: Some(1),
+                    ..Default::default()
+                },
+            ),
+        );
+
+        // the sessions of any options are dropped, and unpinned
+        assert!(cache.remove(&path));
+        assert_eq!(cache.len(), 0);
+        assert!(!cache.unpin(&path));
+        assert!(!cache.remove(&path));
+    }
+
+    #[test]
+    fn test_remove_idle() {
+        let mut cache = SessionCache::with_capacity(4);
+        cache.pin(key("a").0);
+        insert(&mut cache, key("a"));
+        insert(&mut cache, key("b"));
+
+        assert_eq!(cache.remove_idle(Duration::from_secs(3600)), 0);
+
+        // the pinned sessions are never idle
+        assert_eq!(cache.remove_idle(Duration::ZERO), 1);
+        assert_eq!(cache.evictions(), 1);
+        assert_eq!(cache.paths(), [key("a").0]);
+    }
+
+    #[test]
+    fn test_get_placed() {
+        let mut cache = SessionCache::with_capacity(4);
+        let (path, _) = key("a");
+        let placed = LoadOptions {
+            device_id: Some(1),
+            ..Default::default()
+        };
+        insert(&mut cache, (path, placed));
+
+        // the sessions on any device are matched if the device is not given
+        let (found, _, _) = cache.get_placed(&path, &Default::default()).unwrap();
+        assert_eq!(found, (path, placed));
+        assert!(cache.get_placed(&path, &placed).is_some());
+
+        let other = LoadOptions {
+            device_id: Some(0),
+            ..Default::default()
+        };
+        assert!(cache.get_placed(&path, &other).is_none());
+        assert!(cache.get_placed(&key("b").0, &Default::default()).is_none());
+    }
+}
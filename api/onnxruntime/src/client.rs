@@ -1,36 +1,171 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering},
+        Arc, PoisonError, Weak,
+    },
+    time::{Duration, Instant},
+};
 
 use ipis::{
     async_trait::async_trait,
-    core::{anyhow::Result, ndarray, value::array::Array},
+    core::{
+        anyhow::{anyhow, bail, Result},
+        ndarray,
+        value::hash::{Hash, Hasher},
+    },
     env::Infer,
-    futures::TryFutureExt,
+    futures::{future::try_join_all, stream, Stream, TryFutureExt},
     path::Path,
-    tokio::sync::Mutex,
+    tokio::{
+        self,
+        io::{AsyncReadExt, AsyncSeekExt},
+        sync::{Mutex, OnceCell, RwLock, Semaphore},
+    },
 };
 use ipnis_common::{
-    model::Model,
-    onnxruntime::{environment::Environment, session::Session, tensor::OrtOwnedTensor},
-    tensor::{dynamic::DynamicTensorData, Tensor},
+    error::IpnisError,
+    model::{Model, ModelMetadata},
+    onnxruntime::{
+        environment::Environment,
+        session::{RunOptions, Session, SessionBuilder},
+        tensor::DynOrtTensor,
+        AllocatorType, ExecutionMode, LoggingLevel, MemType,
+    },
+    tensor::{batch, dynamic::DynamicTensorData, ty::TensorType, AsTensorData, Tensor, TensorData},
     Ipnis,
 };
 use ipsis_common::Ipsis;
 use ipsis_modules_local::IpsisLocal;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
-use crate::config::ClientConfig;
+#[cfg(feature = "metrics")]
+use crate::metrics::LatencyMetrics;
+use crate::{
+    cache::{SessionCache, SessionKey},
+    config::{Allocator, CallOptions, ClientConfig, Compression, ExecutionProvider, LoadOptions},
+    disk::DiskCache,
+    file::ModelFile,
+    guard::{self, ModelScan},
+};
 
 pub type IpnisClient = IpnisClientInner<::ipiis_api::client::IpiisClient>;
 
+/// The maximum number of the accepted inferences.
+const MAX_RUNNING: u32 = u32::MAX >> 3;
+
+/// The run option shrinking the memory arenas of the given devices at the end of the run.
+const ARENA_SHRINKAGE: &str = "memory.enable_memory_arena_shrinkage";
+
+/// The callback receiving the downloaded bytes and the total bytes.
+type Progress = dyn Fn(u64, u64) + Send + Sync;
+
+/// The readiness of the client, to be served as a probe.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HealthStatus {
+    /// Whether the storage backend has responded.
+    pub backend: bool,
+    /// The number of the sessions which are currently cached.
+    pub num_cached_sessions: usize,
+    /// The error of the failed check, if any.
+    pub last_error: Option<String>,
+}
+
+impl HealthStatus {
+    pub fn is_healthy(&self) -> bool {
+        self.backend && self.last_error.is_none()
+    }
+}
+
+/// The threading of the sessions, to confirm the thread tuning.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ThreadInfo {
+    pub intra_op_threads: u8,
+    pub inter_op_threads: u8,
+    pub execution_mode: ExecutionMode,
+    /// Whether the values are the requested ones, rather than the effective ones.
+    ///
+    /// The ONNX Runtime does not expose the effective values, which may be clamped
+    /// into the number of the cores, so it is always `true` for now.
+    pub is_requested: bool,
+}
+
+/// The breakdown of the latency of an inference, without enabling the profiling.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct InferenceTimings {
+    /// The duration waiting for the session and the permit of `max_concurrent_inferences`.
+    ///
+    /// Note that it includes loading the session on the cache misses.
+    pub lock_wait: Duration,
+    /// The duration until the blocking thread has started the inference.
+    pub dispatch: Duration,
+    /// The duration of the inference itself (`session.run`).
+    pub run: Duration,
+    /// The duration converting the outputs into the tensors, including the casts.
+    pub conversion: Duration,
+}
+
+impl ::std::ops::AddAssign for InferenceTimings {
+    fn add_assign(&mut self, rhs: Self) {
+        self.lock_wait += rhs.lock_wait;
+        self.dispatch += rhs.dispatch;
+        self.run += rhs.run;
+        self.conversion += rhs.conversion;
+    }
+}
+
+/// The snapshot of the cache statistics, to tune `ClientConfig.max_cached_sessions`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// The total bytes of the models fetched from the storage, counted once verified.
+    pub downloaded_bytes: u64,
+}
+
+#[derive(Default)]
+struct Metrics {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    downloaded_bytes: AtomicU64,
+}
+
 pub struct IpnisClientInner<IpiisClient> {
     pub ipiis: IpiisClient,
     config: ClientConfig,
-    environment: Environment,
+    environment: Arc<Environment>,
     /// ## Thread-safe
     /// It's safe to invoke Run() on the same session object in multiple threads.
     /// No need for any external synchronization.
     ///
     /// * Source: https://github.com/microsoft/onnxruntime/issues/114#issuecomment-444725508
-    sessions: Mutex<HashMap<Path, Arc<Session>>>,
+    sessions: Arc<RwLock<SessionCache>>,
+    /// Stops the background tasks, such as the sweeper of the idle sessions.
+    background: CancellationToken,
+    /// The latest models of the logical names, refreshed by `watch_alias`.
+    aliases: RwLock<HashMap<String, Model>>,
+    /// The sessions which are being loaded, shared by the concurrent callers.
+    /// NOTE: the loadings are shared by the requested keys, before being placed on the devices
+    loading: Mutex<HashMap<SessionKey, Arc<OnceCell<(SessionKey, Arc<Session>, ModelScan)>>>>,
+    /// The permits of the concurrent inferences, unlimited if `None`.
+    inferences: Option<Arc<Semaphore>>,
+    /// The number of the ongoing inferences.
+    inflight: Arc<AtomicUsize>,
+    /// The sessions whose memory arenas are shrunk after their next runs.
+    shrink_pending: ::std::sync::Mutex<HashSet<SessionKey>>,
+    /// The permits of the accepted inferences, to be drained on shutdown.
+    running: Arc<Semaphore>,
+    metrics: Metrics,
+    /// The log level of the sessions built afterwards.
+    log_level: AtomicU8,
+    /// The index of the device where the next session is placed.
+    next_device: AtomicUsize,
+    #[cfg(feature = "metrics")]
+    latency: LatencyMetrics,
 }
 
 impl<IpiisClient> AsRef<::ipiis_api::client::IpiisClient> for IpnisClientInner<IpiisClient>
@@ -79,121 +214,2512 @@ where
 impl<IpiisClient> IpnisClientInner<IpiisClient> {
     pub async fn with_ipiis_client(ipiis: IpiisClient) -> Result<Self> {
         let config = ClientConfig::try_infer().await?;
-        let log_level = config.log_level;
+        Self::with_ipiis_client_and_config(ipiis, config).await
+    }
+
+    /// Creates a client with the given config, rather than inferring it from the environment.
+    ///
+    /// The config can be made programmatically from `ClientConfig::default()`.
+    pub async fn with_ipiis_client_and_config(
+        ipiis: IpiisClient,
+        config: ClientConfig,
+    ) -> Result<Self> {
+        let environment = Environment::builder()
+            .with_name("ipnis")
+            // The ONNX Runtime's log level can be different than the one of the wrapper crate or the application.
+            .with_log_level(config.log_level)
+            .build()?;
+
+        Ok(Self::with_parts(ipiis, config, environment.into()))
+    }
+
+    /// Creates a client sharing the environment with the others.
+    ///
+    /// The ONNX Runtime recommends a single environment per process.
+    /// Note that `ClientConfig.log_level` is ignored, as the environment is already built.
+    pub async fn with_ipiis_client_and_environment(
+        ipiis: IpiisClient,
+        environment: Arc<Environment>,
+    ) -> Result<Self> {
+        let config = ClientConfig::try_infer().await?;
+
+        Ok(Self::with_parts(ipiis, config, environment))
+    }
+
+    /// Returns the environment, to be shared with the other clients.
+    pub fn environment(&self) -> &Arc<Environment> {
+        &self.environment
+    }
+
+    fn with_parts(ipiis: IpiisClient, config: ClientConfig, environment: Arc<Environment>) -> Self {
+        let log_level = (config.log_level as u8).into();
+        let max_cached_sessions = config.max_cached_sessions;
+        let inferences = config
+            .max_concurrent_inferences
+            .map(|permits| Semaphore::new(permits.max(1)).into());
 
-        Ok(Self {
+        let sessions = Arc::new(RwLock::new(SessionCache::with_capacity(
+            max_cached_sessions,
+        )));
+        let background = CancellationToken::new();
+        if let Some(idle_timeout) = config.idle_timeout {
+            spawn_sweeper(
+                Arc::downgrade(&sessions),
+                background.clone(),
+                idle_timeout,
+                config.sweep_interval,
+            );
+        }
+
+        Self {
             ipiis,
             config,
-            environment: Environment::builder()
-                .with_name("ipnis")
-                // The ONNX Runtime's log level can be different than the one of the wrapper crate or the application.
-                .with_log_level(log_level)
-                .build()?,
-            sessions: Default::default(),
+            environment,
+            sessions,
+            background,
+            aliases: Default::default(),
+            loading: Default::default(),
+            inferences,
+            inflight: Default::default(),
+            shrink_pending: Default::default(),
+            running: Semaphore::new(MAX_RUNNING as usize).into(),
+            metrics: Default::default(),
+            log_level,
+            next_device: Default::default(),
+            #[cfg(feature = "metrics")]
+            latency: LatencyMetrics::new(config.latency_buckets.clone()),
+        }
+    }
+
+    /// Returns the log level of the sessions built afterwards.
+    pub fn log_level(&self) -> LoggingLevel {
+        match self.log_level.load(Ordering::Relaxed) {
+            0 => LoggingLevel::Verbose,
+            1 => LoggingLevel::Info,
+            2 => LoggingLevel::Warning,
+            3 => LoggingLevel::Error,
+            _ => LoggingLevel::Fatal,
+        }
+    }
+
+    /// Changes the log level of the sessions built afterwards.
+    ///
+    /// Note that the ONNX Runtime cannot change the log level of the environment
+    /// after its construction, nor the ones of the cached sessions.
+    /// The cached sessions should be unloaded to be rebuilt with the new log level.
+    pub fn set_log_level(&self, level: LoggingLevel) {
+        self.log_level.store(level as u8, Ordering::Relaxed)
+    }
+
+    /// Stops accepting the new loads and inferences, and waits for the accepted inferences to be finished.
+    /// Then, the cached sessions are released, and the background tasks are stopped.
+    ///
+    /// It takes a shared reference, so that it can be called on the client shared by the server.
+    /// The later calls return immediately, as the client is already shut down.
+    ///
+    /// Note that dropping the client does not wait for anything: the blocking computations
+    /// of the running inferences are finished in background, holding their sessions,
+    /// and their outputs are discarded.
+    pub async fn shutdown(&self) -> Result<()> {
+        // NOTE: the semaphore is fair, so the later inferences wait behind the shutdown
+        if let Ok(permits) = self.running.acquire_many(MAX_RUNNING).await {
+            self.running.close();
+            drop(permits);
+        }
+
+        // release the sessions
+        self.background.cancel();
+        *self.sessions.write().await = SessionCache::with_capacity(self.config.max_cached_sessions);
+        Ok(())
+    }
+
+    /// Returns the statistics of the session cache.
+    pub async fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.metrics.hits.load(Ordering::Relaxed),
+            misses: self.metrics.misses.load(Ordering::Relaxed),
+            evictions: self.sessions.read().await.evictions(),
+            downloaded_bytes: self.metrics.downloaded_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Renders the latency histograms of the inferences per model,
+    /// in the Prometheus text exposition format.
+    #[cfg(feature = "metrics")]
+    pub fn render_metrics(&self) -> String {
+        self.latency.render()
+    }
+
+    /// Returns the number of the sessions which are currently cached.
+    pub async fn num_cached_sessions(&self) -> usize {
+        self.sessions.read().await.len()
+    }
+
+    /// Returns the threading of the sessions of the model, if loaded.
+    ///
+    /// The `deterministic` option is applied, which runs the sessions in a single thread.
+    pub async fn thread_info(&self, path: &Path) -> Option<ThreadInfo> {
+        if self.sessions.read().await.get_all(path).is_empty() {
+            return None;
+        }
+
+        let (intra_op_threads, inter_op_threads, execution_mode) = self.threads();
+        Some(ThreadInfo {
+            intra_op_threads,
+            inter_op_threads,
+            execution_mode,
+            is_requested: true,
+        })
+    }
+
+    /// Checks whether the model is stored, without downloading it.
+    ///
+    /// The models which are already loaded or cached on disk are found locally,
+    /// and the others are asked to the storage backend.
+    pub async fn model_exists(&self, path: &Path) -> Result<bool>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        // find the model loaded
+        if !self.sessions.read().await.get_all(path).is_empty() {
+            return Ok(true);
+        }
+
+        // find the model cached on disk
+        if let Some(dir) = self.config.cache_dir.as_deref() {
+            let disk = DiskCache::new(dir, self.config.max_cache_dir_bytes);
+            if tokio::fs::metadata(disk.filename(path)).await.is_ok() {
+                return Ok(true);
+            }
+        }
+
+        // NOTE: only the existence is checked, rather than fetching the bytes
+        self.ipiis
+            .contains(path)
+            .await
+            .map_err(|error| IpnisError::ModelFetch(error).into())
+    }
+
+    /// Checks whether the storage backend is reachable,
+    /// and whether the given model can be loaded and run if any.
+    ///
+    /// The model is run once with the zero-filled inputs,
+    /// whose dynamic axes are filled with `ClientConfig.warmup_dynamic_size`.
+    pub async fn health_check(&self, path: Option<&Path>) -> Result<HealthStatus>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        // ping the backend
+        let mut last_error = self
+            .ipiis
+            .protocol()
+            .await
+            .err()
+            .map(|error| error.to_string());
+        let backend = last_error.is_none();
+
+        // load and run the model
+        if let (true, Some(path)) = (backend, path) {
+            let outputs = async {
+                let model = self.load_model(path).await?;
+                let inputs = model.example_inputs(self.config.warmup_dynamic_size);
+                self.call_raw(&model, inputs).await
+            };
+            last_error = outputs.await.err().map(|error| error.to_string());
+        }
+
+        Ok(HealthStatus {
+            backend,
+            num_cached_sessions: self.num_cached_sessions().await,
+            last_error,
         })
     }
 
-    async fn load_session(&self, path: &Path) -> Result<Arc<Session>>
+    /// Returns the paths of the models which are currently cached.
+    pub async fn loaded_models(&self) -> Vec<Path> {
+        self.sessions.read().await.paths()
+    }
+
+    /// Loads the model and exempts its sessions from the eviction,
+    /// so that it never pays the cold start.
+    ///
+    /// The pinned sessions are still counted in the metrics, but beyond `max_cached_sessions`.
+    pub async fn pin_model(&self, path: &Path) -> Result<()>
     where
         IpiisClient: Ipsis + Send + Sync,
         <IpiisClient as Ipsis>::Reader: Sync,
     {
-        let mut sessions = self.sessions.lock().await;
+        self.load_model(path).await?;
+        self.sessions.write().await.pin(*path);
+        Ok(())
+    }
 
-        // TODO: hibernate the least used sessions (caching)
+    /// Lets the sessions of the model be evicted again, returning whether it was pinned.
+    pub async fn unpin_model(&self, path: &Path) -> bool {
+        self.sessions.write().await.unpin(path)
+    }
 
-        match sessions.get(path) {
-            Some(session) => Ok(session.clone()),
-            // .onnx.tar files for large (>2GB) models
-            None if path.len > 2_000_000_000 => {
-                // unpack tarball
-                let mut dirname = self.ipiis.download_on_local_tar(path, None).await?;
+    /// Returns the memory of the cached sessions to the system,
+    /// shrinking their memory arenas after their next runs.
+    ///
+    /// The ONNX Runtime only shrinks the arenas at the end of a run,
+    /// so the idle sessions keep their memory until they are run again.
+    pub async fn shrink_memory(&self) {
+        let keys = self.sessions.read().await.keys();
+        self.shrink_pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(keys);
+    }
+
+    /// Returns whether the model is currently cached with any options.
+    pub async fn is_loaded(&self, path: &Path) -> bool {
+        self.sessions.read().await.contains(path)
+    }
+
+    /// Returns the paths of the models which are currently cached,
+    /// with the execution providers which their sessions have been built with.
+    pub async fn loaded_models_with_providers(&self) -> Vec<(Path, ExecutionProvider)> {
+        self.sessions.read().await.providers()
+    }
+
+    /// Returns the number of the inferences which are currently running.
+    pub fn num_inflight_inferences(&self) -> usize {
+        self.inflight.load(Ordering::Relaxed)
+    }
+
+    /// Loads and caches the sessions of the given models concurrently,
+    /// so that the first inferences do not pay for downloading and compiling them.
+    pub async fn warmup(&self, paths: &[Path]) -> Result<()>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        try_join_all(
+            paths
+                .iter()
+                .map(|path| self.load_session(path, &Default::default(), None)),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Loads the sessions of the given models concurrently, and performs an inference on each,
+    /// with the given example inputs or the zero-filled ones.
+    ///
+    /// Unlike `warmup`, the lazy initializations on the first run are also paid here,
+    /// such as compiling the kernels and growing the memory arenas.
+    /// It reduces the latency of the first inferences significantly on `Cuda` and `TensorRt`.
+    /// The dynamic axes of the zero-filled inputs are filled with `ClientConfig.warmup_dynamic_size`.
+    pub async fn warmup_with_examples(&self, models: &[(Path, Option<Vec<Tensor>>)]) -> Result<()>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        try_join_all(models.iter().map(|(path, inputs)| async move {
+            let model = self.load_model(path).await?;
+            let inputs = match inputs {
+                Some(inputs) => inputs.clone(),
+                None => model.example_inputs(self.config.warmup_dynamic_size),
+            };
+            self.call_raw(&model, inputs).await
+        }))
+        .await?;
+        Ok(())
+    }
+
+    /// Loads the session of the model and lends it to the given closure,
+    /// for the ONNX Runtime features which are not wrapped by this client.
+    ///
+    /// The closure runs on the caller's task, so it should not block for long.
+    /// The session may be evicted from the cache meanwhile, but it stays alive
+    /// until the closure returns.
+    pub async fn with_session<F, R>(&self, path: &Path, f: F) -> Result<R>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+        F: FnOnce(&Session) -> R,
+    {
+        let session = self.load_session(path, &Default::default(), None).await?;
+        Ok(f(&session))
+    }
+
+    /// Loads the model and binds the logical name to it,
+    /// so that the later `alias` calls return the new model.
+    ///
+    /// The sessions of the previous model are dropped from the cache,
+    /// but the in-flight inferences are finished on them.
+    pub async fn load_alias(&self, name: &str, path: &Path) -> Result<Model>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        // NOTE: the new model is loaded before swapping, so the callers never wait on it
+        let model = self.load_model(path).await?;
+
+        let previous = self
+            .aliases
+            .write()
+            .await
+            .insert(name.to_string(), model.clone());
+        if let Some(previous) = previous.filter(|previous| previous.path != *path) {
+            self.sessions.write().await.remove(&previous.path);
+        }
+        Ok(model)
+    }
 
-                // find the root model
-                let filename = {
-                    dirname.push("model.onnx");
-                    dirname
-                };
+    /// Returns the latest model bound to the logical name, if any.
+    pub async fn alias(&self, name: &str) -> Option<Model> {
+        self.aliases.read().await.get(name).cloned()
+    }
 
-                let session = self.load_session_from_file(filename)?;
-                sessions.insert(*path, session.clone());
-                Ok(session)
+    /// Resolves the logical name every `ClientConfig.reload_interval`,
+    /// swapping in the new model whenever the resolved path is changed.
+    ///
+    /// It should be spawned by the caller, and runs until the client is shut down.
+    /// The failures are only logged, keeping the previous model.
+    pub async fn watch_alias<F, Fut>(&self, name: &str, resolve: F)
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<Path>>,
+    {
+        let mut interval =
+            tokio::time::interval(self.config.reload_interval.max(Duration::from_millis(1)));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {},
+                () = self.background.cancelled() => break,
             }
-            None => {
-                // download model
-                let filename = self.ipiis.download_on_local(path, None).await?;
 
-                let session = self.load_session_from_file(filename)?;
-                sessions.insert(*path, session.clone());
-                Ok(session)
+            let path = match resolve().await {
+                Ok(path) => path,
+                Err(error) => {
+                    ::ipis::log::warn!("failed to resolve the model: {name}: {error}");
+                    continue;
+                }
+            };
+            let current = self.alias(name).await.map(|model| model.path);
+            if current != Some(path) {
+                match self.load_alias(name, &path).await {
+                    Ok(_) => ::ipis::log::info!("reloaded the model: {name}: {}", path.value),
+                    Err(error) => ::ipis::log::warn!("failed to reload the model: {name}: {error}"),
+                }
             }
         }
     }
 
-    fn load_session_from_file<P>(&self, filename: P) -> Result<Arc<Session>>
+    /// Returns the metadata section of the model, such as its producer and version.
+    pub async fn model_metadata(&self, path: &Path) -> Result<ModelMetadata>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        let session = self.load_session(path, &Default::default(), None).await?;
+        session.as_ref().try_into()
+    }
+
+    /// Checks whether the model contains the quantized operators, such as `QLinearConv`.
+    ///
+    /// The quantized operators are fully supported by the CPU provider.
+    /// The `TensorRt` provider only supports the QDQ format (`QuantizeLinear` and `DequantizeLinear`),
+    /// and the `Cuda` provider runs most of the others on the CPU.
+    ///
+    /// The operator types are found while loading the model, so the cached model is not fetched again.
+    pub async fn is_quantized(&self, path: &Path) -> Result<bool>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        self.load_placed_session(path, &Default::default(), None)
+            .await
+            .map(|(_, _, scan)| scan.quantized)
+    }
+
+    /// Stores the model into the storage, returning its path to be loaded.
+    ///
+    /// The model is validated by building a throwaway session on the CPU before storing,
+    /// ignoring the options of the configured execution provider.
+    pub async fn put_model(&self, model_bytes: &[u8]) -> Result<Path>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        // validate model
+        self.scan_model(model_bytes)?;
+        self.new_generic_session_builder(&Default::default())?
+            .with_model_from_memory(model_bytes)?;
+
+        // NOTE: the path is made of the original bytes, verified once decompressed
+        let path = Path {
+            value: Hash::with_bytes(model_bytes),
+            len: model_bytes.len() as u64,
+        };
+
+        // compress model
+        // NOTE: the incompressible models are stored as they are,
+        //       as the downloads are bounded by the paths
+        let compressed = match self.config.compression {
+            Some(compression) => {
+                let compressed = compression.compress(model_bytes).await?;
+                (compressed.len() < model_bytes.len()).then_some(compressed)
+            }
+            None => None,
+        };
+        let stored = compressed.unwrap_or_else(|| model_bytes.to_vec());
+
+        // store model
+        self.ipiis
+            .put_raw(&path, ::std::io::Cursor::new(stored))
+            .await?;
+        Ok(path)
+    }
+
+    pub async fn put_model_from_file<P>(&self, filename: P) -> Result<Path>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+        P: AsRef<::std::path::Path>,
+    {
+        let model_bytes = tokio::fs::read(filename).await?;
+        self.put_model(&model_bytes).await
+    }
+
+    /// Loads the model from the local filesystem, bypassing the storage.
+    ///
+    /// The session is cached with the path made of the model's hash,
+    /// so the returned model can be used for the inferences as usual.
+    /// Note that the session is pinned until `unload_model`, as it cannot be fetched
+    /// from the storage once evicted.
+    pub async fn load_model_from_file<P>(&self, filename: P) -> Result<Model>
     where
         IpiisClient: Ipsis + Send + Sync,
         <IpiisClient as Ipsis>::Reader: Sync,
         P: AsRef<::std::path::Path> + ::std::fmt::Debug,
     {
-        self.environment
-            .new_session_builder()?
-            .with_optimization_level(self.config.optimization_level)?
-            .with_number_threads(self.config.number_threads.into())?
-            .with_model_from_file(filename)
-            .map(Into::into)
-            .map_err(Into::into)
+        let model_bytes = tokio::fs::read(&filename).await?;
+        let path = Path {
+            value: Hash::with_bytes(&model_bytes),
+            len: model_bytes.len() as u64,
+        };
+
+        self.load_local_session(&path, &model_bytes, |builder| {
+            Ok(builder.with_model_from_file(&filename)?)
+        })
+        .await
     }
-}
 
-#[async_trait]
-impl<IpiisClient> Ipnis for IpnisClientInner<IpiisClient>
-where
-    IpiisClient: Ipsis + Send + Sync,
-    <IpiisClient as Ipsis>::Reader: Sync,
-{
-    async fn protocol(&self) -> Result<String> {
-        Ok("onnxruntime".into())
+    /// Loads the model from the bytes, without the storage and the filesystem,
+    /// such as the ones embedded in the binary.
+    ///
+    /// The model is cached by the hash of the bytes, so the identical models are loaded once.
+    /// Note that the session is pinned until `unload_model`, as it cannot be fetched
+    /// from the storage once evicted.
+    pub async fn load_model_from_bytes(&self, model_bytes: &[u8]) -> Result<Model>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        let path = Path {
+            value: Hash::with_bytes(model_bytes),
+            len: model_bytes.len() as u64,
+        };
+
+        self.load_local_session(&path, model_bytes, |builder| {
+            Ok(builder.with_model_from_memory(model_bytes)?)
+        })
+        .await
     }
 
-    /// ## Thread-safe
-    /// This method is thread-safe: https://github.com/microsoft/onnxruntime/issues/114#issuecomment-444725508
-    async fn call_raw(&self, model: &Model, inputs: Vec<Tensor>) -> Result<Vec<Tensor>> {
-        // load a model
-        let session = self.load_session(&model.path).await?;
+    /// Loads and pins the session of the model which is not in the storage.
+    async fn load_local_session<F>(&self, path: &Path, model_bytes: &[u8], load: F) -> Result<Model>
+    where
+        F: Fn(SessionBuilder<'_>) -> Result<Session>,
+    {
+        let options = LoadOptions::default();
 
-        // perform the inference
-        let outputs: Vec<OrtOwnedTensor<f32, ndarray::IxDyn>> = session.run(&inputs)?;
+        // NOTE: the read lock should be released before acquiring the write lock
+        let cached = self.sessions.read().await.get_placed(path, &options);
+        let session = match cached {
+            Some((_, session, _)) => {
+                self.sessions.write().await.pin(*path);
+                session
+            }
+            None => {
+                let scan = self.scan_model(model_bytes)?;
+                self.validate_free_dimension_overrides(&scan)?;
+                let options = self.place(&options);
+                let (session, provider) = self.load_session_with(path, &options, load)?;
 
-        // collect outputs
-        let outputs = model
-            .outputs
-            .iter()
-            .zip(outputs)
-            .map(|(shape, output)| Tensor {
-                name: shape.name.to_string(),
-                data: DynamicTensorData::F32(Array(output.to_owned().into_shared())).into(),
-            })
-            .collect();
+                // NOTE: the session is pinned together, so that it is never evicted meanwhile
+                let mut sessions = self.sessions.write().await;
+                sessions.pin(*path);
+                sessions.insert((*path, options), session, provider, scan)
+            }
+        };
+        to_model(path, &session)
+    }
 
-        Ok(outputs)
+    /// Finishes profiling the cached sessions of the model,
+    /// returning the filenames of the written profiles.
+    ///
+    /// Note that the sessions do not profile anymore once finished.
+    pub async fn end_profiling(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        if !self.config.profiling {
+            bail!("profiling is not enabled")
+        }
+
+        self.sessions
+            .read()
+            .await
+            .get_all(path)
+            .into_iter()
+            .map(|session| session.end_profiling().map(Into::into).map_err(Into::into))
+            .collect()
     }
 
-    async fn load_model(&self, path: &Path) -> Result<Model> {
-        let session = self.load_session(path).await?;
+    /// Loads the model with the options overriding the client config.
+    pub async fn load_model_with_options(&self, path: &Path, options: &LoadOptions) -> Result<Model>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        let session = self.load_session(path, options, None).await?;
+        to_model(path, &session)
+    }
 
-        Ok(Model {
-            path: *path,
-            inputs: session
-                .inputs
-                .iter()
-                .map(TryInto::try_into)
-                .collect::<Result<_>>()?,
-            outputs: session
-                .outputs
-                .iter()
-                .map(TryInto::try_into)
-                .collect::<Result<_>>()?,
+    /// Loads the model, reporting the downloaded bytes and the total bytes.
+    ///
+    /// Note that the progress is not reported if the model is cached or being loaded by others.
+    pub async fn load_model_with_progress<F>(&self, path: &Path, progress: F) -> Result<Model>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+        F: Fn(u64, u64) + Send + Sync,
+    {
+        let session = self
+            .load_session(path, &Default::default(), Some(&progress))
+            .await?;
+        to_model(path, &session)
+    }
+
+    /// Performs the inference with the options overriding the client config,
+    /// such as the options of the session and the timeout.
+    pub async fn call_raw_with_options(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+        options: &CallOptions,
+    ) -> Result<Vec<Tensor>>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        self.run(model, inputs, None, options, None, Vec::new())
+            .await
+            .map(|(outputs, _)| outputs)
+    }
+
+    /// Performs the inference, returning `IpnisError::Cancelled` as soon as the token is cancelled.
+    ///
+    /// Loading the model and waiting for the permit of `max_concurrent_inferences` are cancelled
+    /// as well. Note that the ongoing computation cannot be preempted, so the cancellation only
+    /// abandons waiting on it, but the permit is released immediately, letting the others make progress.
+    pub async fn call_raw_cancellable(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Tensor>>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        self.run(
+            model,
+            inputs,
+            None,
+            &Default::default(),
+            Some(cancel),
+            Vec::new(),
+        )
+        .await
+        .map(|(outputs, _)| outputs)
+    }
+
+    /// Performs the inference, returning the breakdown of its latency as well.
+    pub async fn call_raw_timed(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+    ) -> Result<(Vec<Tensor>, InferenceTimings)>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        self.run(model, inputs, None, &Default::default(), None, Vec::new())
+            .await
+    }
+
+    /// Performs the inference, writing the outputs into the given buffers of the same names.
+    ///
+    /// The buffers having the same types and the same shapes with the outputs are reused
+    /// and returned, saving the allocations of the fixed-shape models.
+    /// The other outputs, such as the dynamic-shaped ones, are allocated as usual.
+    ///
+    /// Note that the shared buffers are copied on write, so they should not be cloned.
+    ///
+    /// NOTE: the outputs are not bound to the device memory on GPU, as the onnxruntime bindings
+    /// do not expose `IoBinding`; they are copied into the buffers on the host instead
+    pub async fn call_raw_into(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+        buffers: Vec<Tensor>,
+    ) -> Result<Vec<Tensor>>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        self.run(model, inputs, None, &Default::default(), None, buffers)
+            .await
+            .map(|(outputs, _)| outputs)
+    }
+
+    /// Performs the inferences step by step, yielding the outputs of each step.
+    ///
+    /// The inputs of the next step are made of the outputs by `next`, such as feeding
+    /// the generated token and the updated state back into the autoregressive models.
+    /// The stream ends when `next` returns `None`, after `max_steps` steps,
+    /// or right after yielding an error.
+    ///
+    /// The session is loaded once and kept in the cache across the steps.
+    pub fn infer_stream<'a, F>(
+        &'a self,
+        model: &'a Model,
+        inputs: Vec<Tensor>,
+        max_steps: usize,
+        next: F,
+    ) -> impl Stream<Item = Result<Vec<Tensor>>> + 'a
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+        F: FnMut(&[Tensor]) -> Option<Vec<Tensor>> + Send + 'a,
+    {
+        stream::unfold(
+            (Some(inputs), 0, next),
+            move |(inputs, step, mut next)| async move {
+                let inputs = inputs?;
+                if step >= max_steps {
+                    return None;
+                }
+
+                match self.call_raw(model, inputs).await {
+                    Ok(outputs) => {
+                        let inputs = next(&outputs);
+                        Some((Ok(outputs), (inputs, step + 1, next)))
+                    }
+                    Err(error) => Some((Err(error.into()), (None, step + 1, next))),
+                }
+            },
+        )
+    }
+
+    async fn load_session(
+        &self,
+        path: &Path,
+        options: &LoadOptions,
+        progress: Option<&Progress>,
+    ) -> Result<Arc<Session>>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        self.load_placed_session(path, options, progress)
+            .await
+            .map(|(_, session, _)| session)
+    }
+
+    /// Loads the session, returning the key which it has been placed and cached with,
+    /// and the facts of the model found while loading it.
+    ///
+    /// The model is placed on the next device only if a new session is loaded,
+    /// so the cached sessions on any device are reused.
+    async fn load_placed_session(
+        &self,
+        path: &Path,
+        options: &LoadOptions,
+        progress: Option<&Progress>,
+    ) -> Result<(SessionKey, Arc<Session>, ModelScan)>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        // reject the new loads after shutdown
+        if self.running.is_closed() {
+            bail!("the client has been shut down")
+        }
+
+        // find a cached session
+        if let Some(cached) = self.sessions.read().await.get_placed(path, options) {
+            self.metrics.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(cached);
+        }
+        self.metrics.misses.fetch_add(1, Ordering::Relaxed);
+
+        // join the in-flight loading of the same model, if any
+        let key = (*path, *options);
+        let cell = self.loading.lock().await.entry(key).or_default().clone();
+        let session: Result<_> = cell
+            .get_or_try_init(|| async {
+                // NOTE: the others may have finished loading it since the cache is checked,
+                //       having already removed their cell from `loading`
+                if let Some(cached) = self.sessions.read().await.get_placed(path, options) {
+                    return Ok(cached);
+                }
+
+                let options = self.place(options);
+                let (session, provider, scan) =
+                    self.download_session(path, &options, progress).await?;
+
+                // store the session, keeping the one which has been finished first
+                let key = (*path, options);
+                let session =
+                    self.sessions
+                        .write()
+                        .await
+                        .insert(key, session, provider, scan.clone());
+                Ok((key, session, scan))
+            })
+            .await
+            .cloned();
+
+        // let the later callers start over, whether the loading has been succeeded or not
+        {
+            let mut loading = self.loading.lock().await;
+            if loading
+                .get(&key)
+                .map(|e| Arc::ptr_eq(e, &cell))
+                .unwrap_or_default()
+            {
+                loading.remove(&key);
+            }
+        }
+        session
+    }
+
+    /// Places the session on the next device in turn, if not given.
+    fn place(&self, options: &LoadOptions) -> LoadOptions {
+        let device_ids = &self.config.device_ids;
+        match options.device_id {
+            None if !device_ids.is_empty() => {
+                let index = self.next_device.fetch_add(1, Ordering::Relaxed) % device_ids.len();
+                LoadOptions {
+                    device_id: Some(device_ids[index]),
+                    ..*options
+                }
+            }
+            _ => *options,
+        }
+    }
+
+    async fn download_session(
+        &self,
+        path: &Path,
+        options: &LoadOptions,
+        progress: Option<&Progress>,
+    ) -> Result<(Arc<Session>, ExecutionProvider, ModelScan)>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        // NOTE: the cache lock is not held here, so other models can be served while downloading
+        // NOTE: only fetching is retried, as the invalid models would fail again
+        let span = ::tracing::info_span!(
+            "download",
+            path = %path.value,
+            len = path.len,
+            elapsed_ms = ::tracing::field::Empty,
+        );
+        let (file, scan) = timed(
+            &span,
+            self.with_retries(|| self.fetch_model(path, progress)),
+        )
+        .instrument(span.clone())
+        .await?;
+        self.validate_free_dimension_overrides(&scan)?;
+
+        let span = ::tracing::info_span!(
+            "build",
+            path = %path.value,
+            len = path.len,
+            elapsed_ms = ::tracing::field::Empty,
+        );
+        let _span = span.enter();
+        let instant = Instant::now();
+        // NOTE: the private files are removed as soon as the session is built,
+        //       which is fine even if the ONNX Runtime has mapped them into memory
+        let session = self.load_session_from_file(path, file, options);
+        span.record("elapsed_ms", instant.elapsed().as_millis() as u64);
+        session.map(|(session, provider)| (session, provider, scan))
+    }
+
+    async fn fetch_model(
+        &self,
+        path: &Path,
+        progress: Option<&Progress>,
+    ) -> Result<(ModelFile, ModelScan)>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        // NOTE: the declared length is checked first, as it is not trusted yet
+        if let Some(max_model_bytes) = self.config.max_model_bytes {
+            let len = path.len;
+            if len > max_model_bytes {
+                bail!(IpnisError::InvalidModel(anyhow!(
+                    "the model is too large: expected at most {max_model_bytes} bytes, but given {len}"
+                )))
+            }
+        }
+
+        if path.len > 2_000_000_000 {
+            // .onnx.tar files for large (>2GB) models
+            self.fetch_model_tar(path).await
+        } else {
+            let disk = self
+                .config
+                .cache_dir
+                .as_deref()
+                .map(|dir| DiskCache::new(dir, self.config.max_cache_dir_bytes));
+
+            // find the model cached on disk
+            if let Some(disk) = &disk {
+                let filename = disk.filename(path);
+                if let Ok(cached) = tokio::fs::File::open(&filename).await {
+                    // NOTE: the cached models are always verified, as they may be modified by others
+                    // NOTE: the cached models are copied into private files while being verified
+                    //       chunk by chunk, so the verified contents are built even if replaced meanwhile
+                    match copy_model_verified(path, cached, self.config.chunk_size).await {
+                        Ok(file) => return self.unpack_model(path, file).await,
+                        Err(_) => {
+                            tokio::fs::remove_file(&filename).await.ok();
+                        }
+                    }
+                }
+            }
+
+            // download model
+            let file = match progress {
+                Some(progress) => self.download_with_progress(path, progress).await,
+                None => self
+                    .ipiis
+                    .download_on_local(path, None)
+                    .await
+                    .map(ModelFile::shared),
+            };
+            let file = match file {
+                // NOTE: the mismatched lengths are reported as they are
+                Err(error) if error.is::<IpnisError>() => return Err(error),
+                Err(error) => return Err(self.fetch_error(path, error).await.into()),
+                Ok(file) => file,
+            };
+            // NOTE: the bytes read from the storage are counted, which may be compressed
+            let len = tokio::fs::metadata(&file).await?.len();
+            let downloaded = file.as_ref().to_path_buf();
+
+            // verify model
+            // NOTE: the path is made of the decompressed model, so it is verified once decompressed
+            let verified = match self.decompress_model(path, file).await {
+                Ok(file) => {
+                    let verified = if path.len > self.config.streaming_threshold {
+                        // NOTE: the large models are read chunk by chunk, bounding the memory usage
+                        verify_model_chunked(
+                            path,
+                            file.as_ref(),
+                            self.config.chunk_size,
+                            self.config.verify_hash,
+                        )
+                        .await
+                    } else if self.config.verify_hash {
+                        verify_model(path, file.as_ref()).await
+                    } else {
+                        verify_model_len(path, file.as_ref()).await
+                    };
+                    verified.map(|()| file)
+                }
+                Err(error) => Err(error),
+            };
+
+            let file = match verified {
+                Ok(file) => file,
+                Err(error) => {
+                    // drop the broken model so that it can be fetched again
+                    tokio::fs::remove_file(&downloaded).await.ok();
+                    return Err(error);
+                }
+            };
+            self.metrics
+                .downloaded_bytes
+                .fetch_add(len, Ordering::Relaxed);
+
+            // NOTE: the models are cached decompressed, so that they can be verified again
+            let stored = match &disk {
+                Some(disk) => ModelFile::shared(disk.store(path, file.as_ref()).await?),
+                None => file,
+            };
+            self.unpack_model(path, stored).await
+        }
+    }
+
+    /// Unpacks the verified model, and scans it with `model_guard`.
+    async fn unpack_model(&self, path: &Path, file: ModelFile) -> Result<(ModelFile, ModelScan)> {
+        // .onnx.tar files for the models with the external data (weights in separate files)
+        // NOTE: the tarball is unpacked locally, as it has been already fetched and verified
+        if is_tarball(file.as_ref()).await? {
+            let name = path.value.to_string();
+            let file = ModelFile::unpack_private(&name, file.as_ref(), "model.onnx")
+                .await
+                .map_err(|error| {
+                    IpnisError::InvalidModel(anyhow!("failed to unpack the model: {error}"))
+                })?;
+            if tokio::fs::metadata(&file).await.is_err() {
+                bail!(IpnisError::InvalidModel(anyhow!(
+                    "No such root model in the tarball: model.onnx"
+                )))
+            }
+
+            // NOTE: the external data are not counted into the initializers
+            let scan = self.scan_model_file(file.as_ref()).await?;
+            return Ok((file, scan));
+        }
+
+        // NOTE: the external data are detected by the locations of the tensors, not by their names
+        let scan = self.scan_model_file(file.as_ref()).await?;
+        if scan.external_data {
+            bail!(IpnisError::InvalidModel(anyhow!(
+                "the model refers to the external data, which should be packed together as a .onnx.tar file"
+            )))
+        }
+        Ok((file, scan))
+    }
+
+    /// Decompresses the model if compressed, returning the decompressed file.
+    ///
+    /// The decompressed bytes are bounded by the length of the path, which is checked
+    /// against `max_model_bytes` before downloading, so they should be verified with the path.
+    async fn decompress_model(&self, path: &Path, file: ModelFile) -> Result<ModelFile> {
+        match Compression::detect(file.as_ref()).await? {
+            Some(compression) => {
+                compression
+                    .decompress(
+                        file.as_ref(),
+                        path.len.min(self.config.max_decompressed_bytes()),
+                        self.config.chunk_size,
+                    )
+                    .await
+            }
+            None => Ok(file),
+        }
+    }
+
+    async fn fetch_model_tar(&self, path: &Path) -> Result<(ModelFile, ModelScan)>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        // unpack tarball
+        let dirname = match self.ipiis.download_on_local_tar(path, None).await {
+            Ok(dirname) => dirname,
+            Err(error) => return Err(self.fetch_error(path, error).await.into()),
+        };
+
+        // NOTE: the tarball is already unpacked, so only the unpacked model is loaded here
+        // NOTE: the external data are resolved relative to the root model
+        // find the root model
+        let filename = dirname.join("model.onnx");
+        // NOTE: the external data are not counted into the initializers
+        let scan = self.scan_model_file(&filename).await?;
+
+        // NOTE: the unpacked bytes are counted, as the tarball itself is not kept
+        let len = dir_len(&dirname).await?;
+        self.metrics
+            .downloaded_bytes
+            .fetch_add(len, Ordering::Relaxed);
+        Ok((ModelFile::shared(filename), scan))
+    }
+
+    /// Tells the missing models apart from the other failures of fetching.
+    ///
+    /// NOTE: the storage backends do not report the missing objects distinctly,
+    /// so the existence is asked once more.
+    async fn fetch_error(&self, path: &Path, error: ::ipis::core::anyhow::Error) -> IpnisError
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        match self.ipiis.contains(path).await {
+            Ok(false) => IpnisError::ModelNotFound(*path),
+            Ok(true) | Err(_) => IpnisError::ModelFetch(error),
+        }
+    }
+
+    async fn with_retries<F, Fut, T>(&self, f: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let max_retries = self.config.max_retries;
+
+        let mut retries = 0;
+        loop {
+            match f().await {
+                Ok(value) => break Ok(value),
+                // NOTE: the missing or the rejected models would be missing or rejected again
+                Err(error)
+                    if retries < max_retries
+                        && !matches!(
+                            error.downcast_ref::<IpnisError>(),
+                            Some(IpnisError::ModelNotFound(_) | IpnisError::InvalidModel(_))
+                        ) =>
+                {
+                    // exponential backoff
+                    let delay = self
+                        .config
+                        .base_delay
+                        .saturating_mul(2u32.saturating_pow(retries));
+                    retries += 1;
+
+                    ::ipis::log::debug!(
+                        "failed to fetch the model, retrying ({retries}/{max_retries}) in {delay:?}: {error}"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(error) => break Err(error),
+            }
+        }
+    }
+
+    /// Downloads the model into a private file, reporting the progress.
+    ///
+    /// The oversized models are rejected here, and the lengths are checked once decompressed,
+    /// even if the hashes are not verified.
+    async fn download_with_progress(&self, path: &Path, progress: &Progress) -> Result<ModelFile>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        let mut reader = self.ipiis.get_raw(path).await?;
+
+        // NOTE: the oversized model is not received beyond its declared length
+        let mut received = 0u64;
+        let (file, len) = ModelFile::write_private(
+            &path.value.to_string(),
+            &mut reader,
+            path.len,
+            self.config.chunk_size,
+            |chunk| {
+                received += chunk.len() as u64;
+
+                // NOTE: no locks are held here, so the callback cannot block the others
+                if received <= path.len {
+                    progress(received, path.len);
+                }
+            },
+        )
+        .await?;
+
+        // NOTE: the broken model is removed on drop
+        // NOTE: the compressed models are shorter than their paths
+        if len > path.len {
+            let expected = path.len;
+            bail!(IpnisError::LengthMismatch {
+                expected,
+                given: len,
+            });
+        }
+        Ok(file)
+    }
+
+    fn load_session_from_file<P>(
+        &self,
+        path: &Path,
+        filename: P,
+        options: &LoadOptions,
+    ) -> Result<(Arc<Session>, ExecutionProvider)>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+        P: AsRef<::std::path::Path> + ::std::fmt::Debug,
+    {
+        self.load_session_with(path, options, |builder| {
+            Ok(builder.with_model_from_file(&filename)?)
+        })
+    }
+
+    /// Builds the session with the given loader of the model,
+    /// falling back to the other execution providers if failed.
+    fn load_session_with<F>(
+        &self,
+        path: &Path,
+        options: &LoadOptions,
+        load: F,
+    ) -> Result<(Arc<Session>, ExecutionProvider)>
+    where
+        F: Fn(SessionBuilder<'_>) -> Result<Session>,
+    {
+        let try_build = |provider| -> Result<Arc<Session>> {
+            let mut builder = self.new_session_builder(provider, options)?;
+            if self.config.profiling {
+                // NOTE: the ONNX Runtime appends the timestamp to the prefix
+                let prefix = self
+                    .config
+                    .profiling_dir
+                    .join(format!("ipnis-{}", path.value));
+                builder = builder.with_profiling(prefix)?;
+            }
+            load(builder).map(Into::into)
+        };
+        let build =
+            |provider| try_build(provider).map_err(|error| IpnisError::SessionBuild(error).into());
+
+        let place = |provider: ExecutionProvider| match options.device_id {
+            Some(device_id) => provider.with_device_id(device_id),
+            None => provider,
+        };
+        let provider = place(self.config.execution_provider);
+        let error = match build(provider) {
+            Ok(session) => return Ok((session, provider)),
+            Err(error) if self.config.strict_execution_provider => return Err(error),
+            Err(error) => error,
+        };
+
+        // fall back to the others in order
+        let fallbacks = match &self.config.fallback_execution_providers[..] {
+            [] => &[ExecutionProvider::Cpu][..],
+            fallbacks => fallbacks,
+        };
+        let mut tried = HashSet::from([provider]);
+        let mut last = (provider, error);
+        for fallback in fallbacks.iter().copied().map(place) {
+            // NOTE: the failed providers are not tried again, even if listed repeatedly
+            if !tried.insert(fallback) {
+                continue;
+            }
+            let (provider, error) = &last;
+            ::ipis::log::warn!(
+                "failed to initialize the execution provider {provider:?}, falling back to {fallback:?}: {error}"
+            );
+            match build(fallback) {
+                Ok(session) => {
+                    ::ipis::log::info!("initialized the execution provider {fallback:?}");
+                    return Ok((session, fallback));
+                }
+                Err(error) => last = (fallback, error),
+            }
+        }
+        Err(last.1)
+    }
+
+    /// Checks whether the model declares the symbolic axes to be overridden.
+    ///
+    /// NOTE: the overridden axes are not symbolic anymore in the built sessions,
+    /// so the axes are found while scanning the model instead.
+    fn validate_free_dimension_overrides(&self, scan: &ModelScan) -> Result<()> {
+        match self
+            .config
+            .free_dimension_overrides
+            .keys()
+            .find(|name| !scan.symbolic_dimensions.contains(name.as_str()))
+        {
+            Some(name) => bail!("No such dimension: {name}"),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the devices whose memory arenas are shrunk after the run, if requested.
+    async fn arena_shrinkage(&self, key: &SessionKey) -> Option<String> {
+        let pending = self
+            .shrink_pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(key);
+        if !(self.config.shrink_memory_after_run || pending) {
+            return None;
+        }
+
+        // NOTE: the ONNX Runtime rejects the devices without the arena-based allocators
+        match self.sessions.read().await.provider(key)? {
+            ExecutionProvider::Cpu => Some("cpu:0".into()),
+            ExecutionProvider::Cuda { device_id } | ExecutionProvider::TensorRt { device_id } => {
+                Some(format!("cpu:0;gpu:{device_id}"))
+            }
+        }
+    }
+
+    /// Scans the model, checking it with `model_guard` if given.
+    fn scan_model(&self, model_bytes: &[u8]) -> Result<ModelScan> {
+        guard::scan(model_bytes, self.config.model_guard.as_ref())
+            .map_err(|error| IpnisError::InvalidModel(error).into())
+    }
+
+    /// Scans the model file chunk by chunk, checking it with `model_guard` if given.
+    async fn scan_model_file(&self, filename: &::std::path::Path) -> Result<ModelScan> {
+        let filename = filename.to_path_buf();
+        let model_guard = self.config.model_guard;
+        tokio::task::spawn_blocking(move || guard::scan_file(&filename, model_guard.as_ref()))
+            .await?
+            .map_err(|error| IpnisError::InvalidModel(error).into())
+    }
+
+    /// Returns the intra-op threads, the inter-op threads and the execution mode of the sessions.
+    fn threads(&self) -> (u8, u8, ExecutionMode) {
+        // NOTE: the parallel reductions may reorder the floating-point operations
+        if self.config.deterministic {
+            (1, 1, ExecutionMode::Sequential)
+        } else {
+            (
+                self.config.intra_op_threads(),
+                self.config.inter_op_threads,
+                self.config.execution_mode,
+            )
+        }
+    }
+
+    /// Creates the session builder with the options shared by all the execution providers.
+    ///
+    /// NOTE: it is also used to validate the models on the CPU,
+    ///       so it should not contain any provider-specific options.
+    fn new_generic_session_builder(&self, options: &LoadOptions) -> Result<SessionBuilder<'_>> {
+        let optimization_level = options
+            .optimization_level
+            .unwrap_or(self.config.optimization_level);
+
+        let (intra_op_threads, inter_op_threads, execution_mode) = self.threads();
+
+        let builder = self
+            .environment
+            .new_session_builder()?
+            .with_optimization_level(optimization_level)?
+            .with_log_level(self.log_level())?
+            .with_number_threads(intra_op_threads.into())?
+            .with_inter_op_number_threads(inter_op_threads.into())?
+            .with_execution_mode(execution_mode)?
+            .with_memory_pattern(self.config.enable_mem_pattern)?
+            .with_deterministic_compute(self.config.deterministic)?;
+
+        // register the custom operators
+        let builder = self.config.custom_op_libraries.iter().try_fold(
+            builder,
+            |builder, filename| -> Result<_> {
+                let display = filename.display();
+                if !filename.exists() {
+                    bail!("No such custom op library: {display}")
+                }
+                builder.with_custom_op_lib(filename).map_err(|error| {
+                    anyhow!("failed to load the custom op library: {display}: {error}")
+                })
+            },
+        )?;
+
+        // pin the symbolic axes
+        let builder = self
+            .config
+            .free_dimension_overrides
+            .iter()
+            .try_fold(builder, |builder, (name, value)| {
+                builder.with_free_dimension_override_by_name(name, *value)
+            })?;
+
+        // pass through the raw options
+        let builder = self.config.session_config_entries.iter().try_fold(
+            builder,
+            |builder, (key, value)| -> Result<_> {
+                builder.with_config_entry(key, value).map_err(|error| {
+                    anyhow!("failed to add the session config entry: {key} = {value}: {error}")
+                })
+            },
+        )?;
+
+        // configure the CPU memory arena
+        match self.config.cpu_arena {
+            Some(arena) => builder
+                .with_cpu_arena(
+                    arena.extend_strategy,
+                    arena.initial_chunk_size,
+                    arena.max_mem,
+                )
+                .map_err(Into::into),
+            None => Ok(builder),
+        }
+    }
+
+    fn new_session_builder(
+        &self,
+        provider: ExecutionProvider,
+        options: &LoadOptions,
+    ) -> Result<SessionBuilder<'_>> {
+        let builder = self.new_generic_session_builder(options)?;
+
+        // limit the GPU memory
+        if let Some(gpu_mem_limit) = self.config.gpu_mem_limit {
+            if !matches!(provider, ExecutionProvider::Cuda { .. }) {
+                if self.config.strict_execution_provider {
+                    bail!("gpu_mem_limit requires the Cuda execution provider, but given {provider:?}")
+                }
+                ::ipis::log::warn!(
+                    "ignoring gpu_mem_limit ({gpu_mem_limit} bytes) for the execution provider {provider:?}"
+                );
+            }
+        }
+
+        // choose the memory of the inputs and the outputs
+        let builder = match (self.config.allocator, provider) {
+            (Allocator::CpuArena, _) => builder,
+            (allocator, ExecutionProvider::Cpu) => {
+                // NOTE: the configured provider may have fallen back to the CPU
+                if self.config.strict_execution_provider
+                    || self.config.execution_provider == ExecutionProvider::Cpu
+                {
+                    bail!("the {allocator:?} allocator requires a GPU execution provider, but given {provider:?}")
+                }
+                ::ipis::log::warn!(
+                    "ignoring the {allocator:?} allocator for the execution provider {provider:?}"
+                );
+                builder
+            }
+            (Allocator::Pinned, _) => builder
+                .with_allocator(AllocatorType::Arena)?
+                .with_memory_type(MemType::CPUOutput)?,
+            (Allocator::Device, _) => builder
+                .with_allocator(AllocatorType::Device)?
+                .with_memory_type(MemType::Default)?,
+        };
+
+        match provider {
+            ExecutionProvider::Cpu => Ok(builder),
+            #[cfg(feature = "cuda")]
+            ExecutionProvider::Cuda { device_id } => match self.config.gpu_mem_limit {
+                Some(gpu_mem_limit) => builder
+                    .with_cuda_memory_limit(
+                        device_id,
+                        gpu_mem_limit,
+                        self.config.arena_extend_strategy,
+                    )
+                    .map_err(Into::into),
+                None => builder.with_cuda(device_id).map_err(Into::into),
+            },
+            #[cfg(feature = "tensorrt")]
+            ExecutionProvider::TensorRt { device_id } => {
+                builder.with_tensorrt(device_id).map_err(Into::into)
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(anyhow!(
+                "the execution provider is not enabled: {provider:?}"
+            )),
+        }
+    }
+
+    async fn run(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+        outputs: Option<&[String]>,
+        options: &CallOptions,
+        cancel: Option<&CancellationToken>,
+        buffers: Vec<Tensor>,
+    ) -> Result<(Vec<Tensor>, InferenceTimings)>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        let span = ::tracing::info_span!(
+            "inference",
+            path = %model.path.value,
+            inputs = inputs.len(),
+            elapsed_ms = ::tracing::field::Empty,
+        );
+        #[cfg(feature = "metrics")]
+        let instant = Instant::now();
+
+        // NOTE: the deadline covers the whole call, including loading the model and queueing
+        let task = self.run_split(model, inputs, outputs, &options.load, buffers);
+        let timeout = options.timeout.or(self.config.timeout);
+        let task = async {
+            match timeout {
+                // NOTE: the timeout does not stop the computation, but only abandons waiting on it
+                Some(timeout) => match tokio::time::timeout(timeout, task).await {
+                    Ok(outputs) => outputs,
+                    Err(_) => bail!("inference timed out: {timeout:?}"),
+                },
+                None => task.await,
+            }
+        };
+
+        // NOTE: the permits are held by the futures, so the cancelled inferences release them at once
+        let task = async {
+            match cancel {
+                Some(cancel) => tokio::select! {
+                    outputs = task => outputs,
+                    () = cancel.cancelled() => Err(IpnisError::Cancelled.into()),
+                },
+                None => task.await,
+            }
+        };
+        let outputs = timed(&span, task).instrument(span.clone()).await;
+
+        #[cfg(feature = "metrics")]
+        self.latency.observe(&model.path, instant.elapsed());
+        outputs
+    }
+
+    /// Performs the inference, splitting the inputs into `max_batch_size` if too large.
+    ///
+    /// The sub-batches are performed in turn, so that their peak memory does not add up.
+    /// The buffers are not reused for the sub-batches, as their shapes are different.
+    async fn run_split(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+        outputs: Option<&[String]>,
+        options: &LoadOptions,
+        buffers: Vec<Tensor>,
+    ) -> Result<(Vec<Tensor>, InferenceTimings)>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        let batches = match self
+            .config
+            .max_batch_size
+            .and_then(|max_batch_size| batch::split(&inputs, max_batch_size))
+        {
+            Some(batches) => batches,
+            None => {
+                return self
+                    .run_traced(model, inputs, outputs, options, buffers)
+                    .await
+            }
+        };
+
+        let mut tensors = Vec::with_capacity(batches.len());
+        let mut timings = InferenceTimings::default();
+        for inputs in batches {
+            let (outputs, batch_timings) = self
+                .run_traced(model, inputs, outputs, options, Vec::new())
+                .await?;
+            tensors.push(outputs);
+            timings += batch_timings;
+        }
+        Ok((batch::concatenate(tensors)?, timings))
+    }
+
+    async fn run_traced(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+        outputs: Option<&[String]>,
+        options: &LoadOptions,
+        buffers: Vec<Tensor>,
+    ) -> Result<(Vec<Tensor>, InferenceTimings)>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+        <IpiisClient as Ipsis>::Reader: Sync,
+    {
+        // reject the new inferences after shutdown
+        let running = self
+            .running
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| anyhow!("the client has been shut down"))?;
+
+        // cast the f32 inputs into f16, as declared by the model
+        let inputs = if self.config.auto_cast_f16 {
+            cast_inputs_into_f16(model, inputs)?
+        } else {
+            inputs
+        };
+
+        // validate inputs
+        if self.config.validate_inputs {
+            model.validate_inputs(&inputs)?;
+        }
+        // NOTE: the malformed elements are always rejected, as they may crash the ONNX Runtime
+        let inputs = inputs
+            .into_iter()
+            .map(Tensor::validate)
+            .collect::<Result<Vec<_>>>()?;
+
+        // load a model
+        let instant = Instant::now();
+        let (key, session, _) = self.load_placed_session(&model.path, options, None).await?;
+        let mut lock_wait = instant.elapsed();
+        let shrinkage = self.arena_shrinkage(&key).await;
+
+        // validate outputs
+        if let Some(outputs) = outputs {
+            if let Some(name) = outputs
+                .iter()
+                .find(|name| !session.outputs.iter().any(|output| &output.name == *name))
+            {
+                bail!("No such output: {name}")
+            }
+        }
+
+        // bind inputs by their names
+        let inputs = bind_inputs(&session, inputs)?;
+        log_shapes(&model.path, "input", &inputs);
+
+        // wait for the other inferences if too many
+        // NOTE: the permit is held here rather than by the computation,
+        //       so that it is released as soon as the caller stops waiting on it
+        let instant = Instant::now();
+        let permit = match &self.inferences {
+            Some(inferences) => Some(inferences.acquire().await?),
+            None => None,
+        };
+        lock_wait += instant.elapsed();
+
+        // perform the inference
+        // NOTE: the blocking computation is moved out of the async runtime
+        let names = outputs.map(<[String]>::to_vec);
+        let inflight = self.inflight.clone();
+        let instant = Instant::now();
+        let task = tokio::task::spawn_blocking(move || {
+            let mut timings = InferenceTimings {
+                lock_wait,
+                dispatch: instant.elapsed(),
+                ..Default::default()
+            };
+
+            inflight.fetch_add(1, Ordering::Relaxed);
+            let tensors = run_session(
+                &session,
+                &inputs,
+                names.as_deref(),
+                buffers,
+                shrinkage.as_deref(),
+                &mut timings,
+            )
+            .map(|tensors| (tensors, timings));
+            inflight.fetch_sub(1, Ordering::Relaxed);
+
+            // NOTE: the shutdown waits for the computation to be finished, even if abandoned
+            drop(running);
+            tensors
+        });
+        let (mut tensors, mut timings) = task.await??;
+        drop(permit);
+
+        // cast the f16 outputs into f32
+        if self.config.auto_cast_f16 {
+            let instant = Instant::now();
+            tensors = tensors
+                .into_iter()
+                .map(cast_output_into_f32)
+                .collect::<Result<_>>()?;
+            timings.conversion += instant.elapsed();
+        }
+
+        log_shapes(&model.path, "output", &tensors);
+
+        // sort outputs in the requested order
+        let tensors = match outputs {
+            Some(outputs) => outputs
+                .iter()
+                .map(|name| Tensor::find(&mut tensors, name))
+                .collect::<Result<_>>()?,
+            None => tensors,
+        };
+        Ok((tensors, timings))
+    }
+}
+
+#[async_trait]
+impl<IpiisClient> Ipnis for IpnisClientInner<IpiisClient>
+where
+    IpiisClient: Ipsis + Send + Sync,
+    <IpiisClient as Ipsis>::Reader: Sync,
+{
+    async fn protocol(&self) -> Result<String, IpnisError> {
+        Ok("onnxruntime".into())
+    }
+
+    /// ## Thread-safe
+    /// This method is thread-safe: https://github.com/microsoft/onnxruntime/issues/114#issuecomment-444725508
+    async fn call_raw(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+    ) -> Result<Vec<Tensor>, IpnisError> {
+        let (outputs, _) = self
+            .run(model, inputs, None, &Default::default(), None, Vec::new())
+            .await?;
+        Ok(outputs)
+    }
+
+    async fn call_raw_with_outputs(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+        outputs: &[String],
+    ) -> Result<Vec<Tensor>, IpnisError> {
+        let (outputs, _) = self
+            .run(
+                model,
+                inputs,
+                Some(outputs),
+                &Default::default(),
+                None,
+                Vec::new(),
+            )
+            .await?;
+        Ok(outputs)
+    }
+
+    async fn load_model(&self, path: &Path) -> Result<Model, IpnisError> {
+        Ok(self
+            .load_model_with_options(path, &Default::default())
+            .await?)
+    }
+
+    /// The session is released as soon as the in-flight inferences are finished.
+    async fn unload_model(&self, path: &Path) -> Result<bool, IpnisError> {
+        Ok(self.sessions.write().await.remove(path))
+    }
+
+    async fn list_models(&self) -> Result<Vec<Path>, IpnisError> {
+        Ok(self.loaded_models().await)
+    }
+}
+
+/// Drops the idle sessions periodically in background, until the client is dropped or shut down.
+fn spawn_sweeper(
+    sessions: Weak<RwLock<SessionCache>>,
+    token: CancellationToken,
+    idle_timeout: Duration,
+    interval: Duration,
+) {
+    let runtime = match tokio::runtime::Handle::try_current() {
+        Ok(runtime) => runtime,
+        Err(_) => {
+            ::ipis::log::warn!("ignoring idle_timeout out of the async runtime");
+            return;
+        }
+    };
+
+    runtime.spawn(async move {
+        let mut interval = tokio::time::interval(interval.max(Duration::from_millis(1)));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {},
+                () = token.cancelled() => break,
+            }
+
+            let sessions = match sessions.upgrade() {
+                Some(sessions) => sessions,
+                None => break,
+            };
+            let removed = sessions.write().await.remove_idle(idle_timeout);
+            if removed > 0 {
+                ::ipis::log::debug!("dropped {removed} idle sessions");
+            }
+        }
+    });
+}
+
+/// Logs the names, the types and the shapes of the tensors, but not their values.
+fn log_shapes(path: &Path, kind: &str, tensors: &[Tensor]) {
+    // NOTE: the shapes are not even collected unless the debug logs are enabled
+    if ::ipis::log::log_enabled!(::ipis::log::Level::Debug) {
+        for tensor in tensors {
+            let name = &tensor.name;
+            let ty = tensor.ty();
+            let shape = tensor.shape().to_vec();
+            ::ipis::log::debug!("{}: {kind}: {name}: {ty:?} {shape:?}", path.value);
+        }
+    }
+}
+
+/// Records the elapsed time of the future into the span.
+async fn timed<F>(span: &::tracing::Span, future: F) -> F::Output
+where
+    F: Future,
+{
+    let instant = Instant::now();
+    let output = future.await;
+    span.record("elapsed_ms", instant.elapsed().as_millis() as u64);
+    output
+}
+
+fn to_model(path: &Path, session: &Session) -> Result<Model> {
+    Ok(Model {
+        path: *path,
+        inputs: session
+            .inputs
+            .iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?,
+        outputs: session
+            .outputs
+            .iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn run_session(
+    session: &Session,
+    inputs: &[Tensor],
+    outputs: Option<&[String]>,
+    mut buffers: Vec<Tensor>,
+    shrinkage: Option<&str>,
+    timings: &mut InferenceTimings,
+) -> Result<Vec<Tensor>> {
+    // NOTE: the whole outputs are computed, but only the requested ones are collected
+    // TODO: take the duration from the ONNX Runtime's profiling when the bindings expose
+    //       the per-run events without the files; the wall clock is used for now
+    let instant = Instant::now();
+    let tensors: Vec<DynOrtTensor<ndarray::IxDyn>> = match shrinkage {
+        Some(devices) => RunOptions::new()
+            .and_then(|options| options.with_config_entry(ARENA_SHRINKAGE, devices))
+            .and_then(|options| session.run_with_options(inputs, &options)),
+        None => session.run(inputs),
+    }
+    .map_err(|error| IpnisError::Inference(error.into()))?;
+    timings.run = instant.elapsed();
+
+    // validate the number of outputs
+    // NOTE: the outputs are only identified by their order, so they cannot be aligned otherwise
+    let expected = session.outputs.len();
+    let given = tensors.len();
+    if expected != given {
+        bail!("number of the outputs mismatched: expected {expected}, but given {given}");
+    }
+
+    // collect outputs
+    // NOTE: the outputs have the runtime shapes, rather than the declared (symbolic) ones
+    let instant = Instant::now();
+    let outputs = session
+        .outputs
+        .iter()
+        .zip(tensors)
+        .filter(|(shape, _)| {
+            outputs
+                .map(|outputs| outputs.contains(&shape.name))
+                .unwrap_or(true)
+        })
+        .map(|(shape, output)| {
+            let ty = shape.output_type.try_into()?;
+
+            // reuse the buffer of the same name, type and shape if given
+            if let Some(index) = buffers.iter().position(|buffer| buffer.name == shape.name) {
+                let mut buffer = buffers.swap_remove(index);
+                if let TensorData::Dynamic(data) = &mut buffer.data {
+                    if data.ty() == ty && data.assign_ort_tensor(&output)? {
+                        return Ok(buffer);
+                    }
+                }
+            }
+
+            Ok(Tensor {
+                name: shape.name.to_string(),
+                data: DynamicTensorData::from_ort_tensor(ty, output)?.into(),
+            })
+        })
+        .collect();
+    timings.conversion = instant.elapsed();
+    outputs
+}
+
+fn cast_inputs_into_f16(model: &Model, inputs: Vec<Tensor>) -> Result<Vec<Tensor>> {
+    inputs
+        .into_iter()
+        .map(|input| match (model.input(&input.name), input.data) {
+            (Some(shape), TensorData::Dynamic(data @ DynamicTensorData::F32(_)))
+                if shape.ty() == TensorType::F16 =>
+            {
+                Ok(Tensor {
+                    name: input.name,
+                    data: data.cast(TensorType::F16)?.into(),
+                })
+            }
+            (_, data) => Ok(Tensor {
+                name: input.name,
+                data,
+            }),
+        })
+        .collect()
+}
+
+fn cast_output_into_f32(output: Tensor) -> Result<Tensor> {
+    match output.data {
+        TensorData::Dynamic(data @ DynamicTensorData::F16(_)) => Ok(Tensor {
+            name: output.name,
+            data: data.cast(TensorType::F32)?.into(),
+        }),
+        _ => Ok(output),
+    }
+}
+
+fn bind_inputs(session: &Session, mut inputs: Vec<Tensor>) -> Result<Vec<Tensor>> {
+    let inputs_bound = session
+        .inputs
+        .iter()
+        .map(|input| Tensor::find(&mut inputs, &input.name))
+        .collect::<Result<_>>()?;
+
+    match inputs.first() {
+        Some(input) => {
+            let name = &input.name;
+            bail!("unexpected input: {name}")
+        }
+        None => Ok(inputs_bound),
+    }
+}
+
+async fn verify_model(path: &Path, filename: &::std::path::Path) -> Result<()> {
+    let bytes = tokio::fs::read(filename).await?;
+
+    let len = bytes.len() as u64;
+    if len != path.len {
+        let expected = path.len;
+        bail!(IpnisError::LengthMismatch {
+            expected,
+            given: len,
+        });
+    }
+
+    let hash = Hash::with_bytes(&bytes);
+    if hash != path.value {
+        let expected = path.value;
+        bail!(IpnisError::HashMismatch {
+            expected,
+            given: hash,
+        });
+    }
+    Ok(())
+}
+
+/// Copies the model into a private file chunk by chunk, verifying the copied bytes.
+async fn copy_model_verified(
+    path: &Path,
+    reader: tokio::fs::File,
+    chunk_size: usize,
+) -> Result<ModelFile> {
+    let mut hasher = Hasher::default();
+    let (file, len) = ModelFile::write_private(
+        &path.value.to_string(),
+        reader,
+        path.len,
+        chunk_size,
+        |chunk| hasher.update(chunk),
+    )
+    .await?;
+
+    // NOTE: the broken copy is removed on drop
+    if len != path.len {
+        let expected = path.len;
+        bail!(IpnisError::LengthMismatch {
+            expected,
+            given: len,
+        });
+    }
+
+    let hash = hasher.finalize();
+    if hash != path.value {
+        let expected = path.value;
+        bail!(IpnisError::HashMismatch {
+            expected,
+            given: hash,
+        });
+    }
+    Ok(file)
+}
+
+/// Checks only the length of the model, without reading it.
+async fn verify_model_len(path: &Path, filename: &::std::path::Path) -> Result<()> {
+    let len = tokio::fs::metadata(filename).await?.len();
+    if len != path.len {
+        let expected = path.len;
+        bail!(IpnisError::LengthMismatch {
+            expected,
+            given: len,
+        });
+    }
+    Ok(())
+}
+
+async fn verify_model_chunked(
+    path: &Path,
+    filename: &::std::path::Path,
+    chunk_size: usize,
+    verify_hash: bool,
+) -> Result<()> {
+    let mut file = tokio::fs::File::open(filename).await?;
+    let mut hasher = Hasher::default();
+    let mut chunk = vec![0; chunk_size.max(1)];
+
+    let mut len = 0u64;
+    loop {
+        let read = file.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        len += read as u64;
+
+        // stop reading the oversized model
+        if len > path.len {
+            // NOTE: the whole length is reported, rather than the length read so far
+            len = file.metadata().await?.len();
+            break;
+        }
+        if verify_hash {
+            hasher.update(&chunk[..read]);
+        }
+    }
+
+    if len != path.len {
+        let expected = path.len;
+        bail!(IpnisError::LengthMismatch {
+            expected,
+            given: len,
+        });
+    }
+
+    if verify_hash {
+        let hash = hasher.finalize();
+        if hash != path.value {
+            let expected = path.value;
+            bail!(IpnisError::HashMismatch {
+                expected,
+                given: hash,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Sums the lengths of the files in the directory, recursively.
+async fn dir_len(dirname: &::std::path::Path) -> Result<u64> {
+    let mut len = 0;
+    let mut dirs = vec![dirname.to_path_buf()];
+    while let Some(dirname) = dirs.pop() {
+        let mut dir = tokio::fs::read_dir(&dirname).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                len += metadata.len();
+            }
+        }
+    }
+    Ok(len)
+}
+
+async fn is_tarball(filename: &::std::path::Path) -> Result<bool> {
+    // the magic of the POSIX tar archives
+    const MAGIC: &[u8] = b"ustar";
+    const OFFSET: u64 = 257;
+
+    let mut file = tokio::fs::File::open(filename).await?;
+    if file.metadata().await?.len() < OFFSET + MAGIC.len() as u64 {
+        return Ok(false);
+    }
+
+    let mut magic = [0; MAGIC.len()];
+    file.seek(::std::io::SeekFrom::Start(OFFSET)).await?;
+    file.read_exact(&mut magic).await?;
+    Ok(magic == MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        self, node, tensor, value_info, Dim, Graph, MockIpsis, BOOL, FLOAT, INT64,
+    };
+
+    /// The number of the concurrent callers.
+    const NUM_CALLERS: usize = 8;
+
+    async fn client() -> Arc<IpnisClientInner<MockIpsis>> {
+        client_with_config(Default::default()).await
+    }
+
+    async fn client_with_config(config: ClientConfig) -> Arc<IpnisClientInner<MockIpsis>> {
+        IpnisClientInner::with_ipiis_client_and_config(Default::default(), config)
+            .await
+            .map(Arc::new)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_load_model_deduplicated() {
+        testing::block_on(async {
+            let client = client().await;
+            let path = client.ipiis.insert(testing::relu().into_model());
+
+            let loads = (0..NUM_CALLERS).map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.load_model_with_progress(&path, |_, _| {}).await })
+            });
+            for model in try_join_all(loads).await.unwrap() {
+                assert_eq!(model.unwrap().path, path);
+            }
+
+            assert_eq!(client.ipiis.num_gets(), 1);
+            assert_eq!(client.num_cached_sessions().await, 1);
+        })
+    }
+
+    #[test]
+    fn test_load_model_deduplicated_while_finishing() {
+        testing::block_on(async {
+            let client = client().await;
+            let path = client.ipiis.insert(testing::relu().into_model());
+
+            // the later callers arrive while the earlier ones are finishing
+            let loads = (0..NUM_CALLERS * 4).map(|index| {
+                let client = client.clone();
+                tokio::spawn(async move {
+                    for _ in 0..index {
+                        tokio::task::yield_now().await;
+                    }
+                    client.load_model_with_progress(&path, |_, _| {}).await
+                })
+            });
+            for model in try_join_all(loads).await.unwrap() {
+                assert_eq!(model.unwrap().path, path);
+            }
+
+            assert_eq!(client.ipiis.num_gets(), 1);
+            assert_eq!(client.num_cached_sessions().await, 1);
+        })
+    }
+
+    #[test]
+    fn test_load_model_retried_after_failure() {
+        testing::block_on(async {
+            let client = client().await;
+            let model_bytes = testing::relu().into_model();
+            let path = testing::path_of(&model_bytes);
+
+            let loads = (0..NUM_CALLERS).map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.load_model_with_progress(&path, |_, _| {}).await })
+            });
+            for model in try_join_all(loads).await.unwrap() {
+                assert!(model.is_err());
+            }
+
+            // the failed loading is not shared with the later callers
+            client.ipiis.insert(model_bytes);
+            assert!(client.load_model(&path).await.is_ok());
+        })
+    }
+
+    #[test]
+    fn test_load_model_not_found() {
+        testing::block_on(async {
+            let client = client().await;
+            let path = testing::path_of(&testing::relu().into_model());
+
+            match client.load_model(&path).await {
+                Err(IpnisError::ModelNotFound(given)) => assert_eq!(given, path),
+                result => panic!("expected ModelNotFound, but given {result:?}"),
+            }
+
+            // the missing models are not retried
+            let num_gets = client.ipiis.num_gets();
+            let error = client
+                .load_model_with_progress(&path, |_, _| {})
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<IpnisError>(),
+                Some(IpnisError::ModelNotFound(_)),
+            ));
+            assert_eq!(client.ipiis.num_gets(), num_gets + 1);
+        })
+    }
+
+    #[test]
+    fn test_downloaded_bytes_verified() {
+        testing::block_on(async {
+            let client = client().await;
+            let model_bytes = testing::relu().into_model();
+            let path = testing::path_of(&model_bytes);
+
+            // the broken models are not counted
+            let mut broken = model_bytes.clone();
+            *broken.last_mut().unwrap() ^= 0xff;
+            client
+                .ipiis
+                .put_raw(&path, ::std::io::Cursor::new(broken))
+                .await
+                .unwrap();
+            let error = client
+                .load_model_with_progress(&path, |_, _| {})
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<IpnisError>(),
+                Some(IpnisError::HashMismatch { .. }),
+            ));
+            assert_eq!(client.metrics().await.downloaded_bytes, 0);
+
+            client.ipiis.insert(model_bytes);
+            client
+                .load_model_with_progress(&path, |_, _| {})
+                .await
+                .unwrap();
+            assert_eq!(client.metrics().await.downloaded_bytes, path.len);
+        })
+    }
+
+    /// Encodes the model of `y = Relu(x)`, whose zero-filled initializer compresses well.
+    fn compressible() -> Vec<u8> {
+        testing::relu()
+            .initializer(tensor("unused", &[1024], &[0.0; 1024]))
+            .into_model()
+    }
+
+    #[test]
+    fn test_put_model_compressed() {
+        let model_bytes = compressible();
+
+        testing::block_on(async {
+            let client = client_with_config(ClientConfig {
+                compression: Some(Compression::Zstd),
+                ..Default::default()
+            })
+            .await;
+
+            // the path is made of the decompressed model
+            let path = client.put_model(&model_bytes).await.unwrap();
+            assert_eq!(path, testing::path_of(&model_bytes));
+
+            client
+                .load_model_with_progress(&path, |_, _| {})
+                .await
+                .unwrap();
+            assert!(client.metrics().await.downloaded_bytes < path.len);
+        })
+    }
+
+    #[test]
+    fn test_decompressed_len_mismatched() {
+        let model_bytes = compressible();
+
+        testing::block_on(async {
+            let client = client().await;
+            let path = testing::path_of(&model_bytes);
+
+            // the decompressed model is shorter than its path
+            let compressed = Compression::Zstd
+                .compress(&model_bytes[..model_bytes.len() - 1])
+                .await
+                .unwrap();
+            client
+                .ipiis
+                .put_raw(&path, ::std::io::Cursor::new(compressed))
+                .await
+                .unwrap();
+
+            let error = client
+                .load_model_with_progress(&path, |_, _| {})
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<IpnisError>(),
+                Some(IpnisError::LengthMismatch { .. }),
+            ));
+            assert_eq!(client.metrics().await.downloaded_bytes, 0);
+        })
+    }
+
+    #[test]
+    fn test_execution_modes() {
+        // y = Relu(x) + Neg(x), whose branches can run in parallel
+        let model_bytes = Graph::default()
+            .node(node("Relu", &["x"], &["a"]))
+            .node(node("Neg", &["x"], &["b"]))
+            .node(node("Add", &["a", "b"], &["y"]))
+            .input(value_info(
+                "x",
+                FLOAT,
+                &[Dim::Param("batch"), Dim::Value(2)],
+            ))
+            .output(value_info(
+                "y",
+                FLOAT,
+                &[Dim::Param("batch"), Dim::Value(2)],
+            ))
+            .into_model();
+        let inputs = vec![Tensor::from((
+            "x",
+            ndarray::array![[-1.0f32, 2.0], [3.0, -4.0]].into_dyn(),
+        ))];
+
+        testing::block_on(async {
+            let mut outputs = Vec::new();
+            for execution_mode in [ExecutionMode::Sequential, ExecutionMode::Parallel] {
+                let client = client_with_config(ClientConfig {
+                    execution_mode,
+                    inter_op_threads: 2,
+                    ..Default::default()
+                })
+                .await;
+                let model = client.load_model_from_bytes(&model_bytes).await.unwrap();
+                outputs.push(client.call_raw(&model, inputs.clone()).await.unwrap());
+            }
+
+            let expected =
+                Tensor::from(("y", ndarray::array![[1.0f32, -2.0], [3.0, 4.0]].into_dyn()));
+            assert_eq!(outputs, [[expected.clone()], [expected]]);
+        })
+    }
+
+    #[test]
+    fn test_deterministic() {
+        // y = x * w, whose reductions may be split across the threads
+        let weights: Vec<f32> = (0..64 * 8).map(|i| (i as f32 * 0.37).sin()).collect();
+        let model_bytes = Graph::default()
+            .node(node("MatMul", &["x", "w"], &["y"]))
+            .initializer(tensor("w", &[64, 8], &weights))
+            .input(value_info(
+                "x",
+                FLOAT,
+                &[Dim::Param("batch"), Dim::Value(64)],
+            ))
+            .output(value_info(
+                "y",
+                FLOAT,
+                &[Dim::Param("batch"), Dim::Value(8)],
+            ))
+            .into_model();
+        let inputs = vec![Tensor::from((
+            "x",
+            ndarray::Array::from_shape_fn((4, 64), |(i, j)| ((i * 64 + j) as f32 * 0.11).cos())
+                .into_dyn(),
+        ))];
+
+        fn to_bits(outputs: &[Tensor]) -> Vec<u32> {
+            match &outputs[0].data {
+                TensorData::Dynamic(DynamicTensorData::F32(y)) => {
+                    y.iter().map(|value| value.to_bits()).collect()
+                }
+                data => panic!("expected F32, but given {data:?}"),
+            }
+        }
+
+        testing::block_on(async {
+            let mut outputs = Vec::new();
+            // NOTE: the new clients build the new sessions, not reusing the arenas
+            for _ in 0..2 {
+                let client = client_with_config(ClientConfig {
+                    deterministic: true,
+                    ..Default::default()
+                })
+                .await;
+                let model = client.load_model_from_bytes(&model_bytes).await.unwrap();
+                for _ in 0..2 {
+                    let y = client.call_raw(&model, inputs.clone()).await.unwrap();
+                    outputs.push(to_bits(&y));
+                }
+            }
+
+            assert_eq!(outputs[0].len(), 4 * 8);
+            assert!(outputs.iter().all(|y| y == &outputs[0]));
+        })
+    }
+
+    #[test]
+    fn test_optional_outputs() {
+        // y, mask = Dropout(x), whose mask is optional
+        let model_bytes = Graph::default()
+            .node(node("Dropout", &["x"], &["y", "mask"]))
+            .input(value_info(
+                "x",
+                FLOAT,
+                &[Dim::Param("batch"), Dim::Value(2)],
+            ))
+            .output(value_info(
+                "y",
+                FLOAT,
+                &[Dim::Param("batch"), Dim::Value(2)],
+            ))
+            .output(value_info(
+                "mask",
+                BOOL,
+                &[Dim::Param("batch"), Dim::Value(2)],
+            ))
+            .into_model();
+        let x = ndarray::array![[-1.0f32, 2.0]].into_dyn();
+        let inputs = vec![Tensor::from(("x", x.clone()))];
+
+        testing::block_on(async {
+            let client = client().await;
+            let model = client.load_model_from_bytes(&model_bytes).await.unwrap();
+
+            // NOTE: the dropout is disabled on inference, keeping the whole inputs
+            let y = Tensor::from(("y", x));
+            let mask = Tensor::from(("mask", ndarray::array![[true, true]].into_dyn()));
+
+            let outputs = client.call_raw(&model, inputs.clone()).await.unwrap();
+            assert_eq!(outputs, [y.clone(), mask.clone()]);
+
+            let outputs = client
+                .call_raw_with_outputs(&model, inputs.clone(), &["mask".into()])
+                .await
+                .unwrap();
+            assert_eq!(outputs, [mask]);
+
+            let outputs = client
+                .call_raw_with_outputs(&model, inputs, &["y".into()])
+                .await
+                .unwrap();
+            assert_eq!(outputs, [y]);
+        })
+    }
+
+    #[test]
+    fn test_bool_inputs() {
+        // y = Not(mask)
+        let model_bytes = Graph::default()
+            .node(node("Not", &["mask"], &["y"]))
+            .input(value_info(
+                "mask",
+                BOOL,
+                &[Dim::Param("batch"), Dim::Value(3)],
+            ))
+            .output(value_info("y", BOOL, &[Dim::Param("batch"), Dim::Value(3)]))
+            .into_model();
+        let inputs = vec![Tensor::from((
+            "mask",
+            ndarray::array![[true, false, true], [false, false, true]].into_dyn(),
+        ))];
+
+        testing::block_on(async {
+            let client = client().await;
+            let model = client.load_model_from_bytes(&model_bytes).await.unwrap();
+
+            let outputs = client.call_raw(&model, inputs).await.unwrap();
+            let expected = ndarray::array![[false, true, false], [true, true, false]];
+            assert_eq!(outputs, [Tensor::from(("y", expected.into_dyn()))]);
+        })
+    }
+
+    #[test]
+    fn test_int64_inputs() {
+        // y = Gather(embeddings, ids), as the embeddings of the transformers
+        let embeddings = [0.0f32, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 3.5];
+        let model_bytes = Graph::default()
+            .node(node("Gather", &["embeddings", "ids"], &["y"]))
+            .initializer(tensor("embeddings", &[4, 2], &embeddings))
+            .input(value_info(
+                "ids",
+                INT64,
+                &[Dim::Param("batch"), Dim::Param("sequence")],
+            ))
+            .output(value_info(
+                "y",
+                FLOAT,
+                &[Dim::Param("batch"), Dim::Param("sequence"), Dim::Value(2)],
+            ))
+            .into_model();
+        let inputs = vec![Tensor::from((
+            "ids",
+            ndarray::array![[3i64, 0, 2]].into_dyn(),
+        ))];
+
+        testing::block_on(async {
+            let client = client().await;
+            let model = client.load_model_from_bytes(&model_bytes).await.unwrap();
+
+            let outputs = client.call_raw(&model, inputs).await.unwrap();
+            let expected = ndarray::array![[[3.0f32, 3.5], [0.0, 0.5], [2.0, 2.5]]];
+            assert_eq!(outputs, [Tensor::from(("y", expected.into_dyn()))]);
+        })
+    }
+
+    #[test]
+    fn test_cancelled_while_waiting() {
+        let model_bytes = testing::relu().into_model();
+        let inputs = vec![Tensor::from((
+            "x",
+            ndarray::array![[-1.0f32, 2.0]].into_dyn(),
+        ))];
+
+        testing::block_on(async {
+            let client = client_with_config(ClientConfig {
+                max_concurrent_inferences: Some(1),
+                ..Default::default()
+            })
+            .await;
+            let model = client.load_model_from_bytes(&model_bytes).await.unwrap();
+
+            // the only permit is taken by the others
+            let permit = client.inferences.as_ref().unwrap().acquire().await.unwrap();
+
+            let cancel = CancellationToken::new();
+            cancel.cancel();
+            let error = client
+                .call_raw_cancellable(&model, inputs.clone(), &cancel)
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<IpnisError>(),
+                Some(IpnisError::Cancelled),
+            ));
+
+            // the cancelled inference does not hold the permit
+            drop(permit);
+            assert_eq!(client.inferences.as_ref().unwrap().available_permits(), 1);
+            assert!(client.call_raw(&model, inputs).await.is_ok());
+        })
+    }
+
+    #[test]
+    fn test_timed_out_while_waiting() {
+        let model_bytes = testing::relu().into_model();
+        let inputs = vec![Tensor::from((
+            "x",
+            ndarray::array![[-1.0f32, 2.0]].into_dyn(),
+        ))];
+
+        testing::block_on(async {
+            let client = client_with_config(ClientConfig {
+                max_concurrent_inferences: Some(1),
+                timeout: Some(Duration::from_secs(3600)),
+                ..Default::default()
+            })
+            .await;
+            let model = client.load_model_from_bytes(&model_bytes).await.unwrap();
+
+            // the only permit is taken by the others
+            let permit = client.inferences.as_ref().unwrap().acquire().await.unwrap();
+
+            // the deadline of the call overrides the client config, covering the queueing
+            let options = CallOptions {
+                timeout: Some(Duration::from_millis(10)),
+                ..Default::default()
+            };
+            let error = client
+                .call_raw_with_options(&model, inputs.clone(), &options)
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("timed out"));
+
+            // the client config is applied otherwise
+            drop(permit);
+            assert!(client.call_raw(&model, inputs).await.is_ok());
         })
     }
 }
@@ -0,0 +1,83 @@
+use async_compression::tokio::bufread::{GzipDecoder, GzipEncoder, ZstdDecoder, ZstdEncoder};
+use ipis::{
+    core::anyhow::{anyhow, bail, Result},
+    tokio::{
+        self,
+        io::{AsyncRead, AsyncReadExt, BufReader},
+    },
+};
+
+use ipnis_common::error::IpnisError;
+
+use crate::{config::Compression, file::ModelFile};
+
+impl Compression {
+    /// The leading bytes of the compressed streams.
+    fn magic(self) -> &'static [u8] {
+        match self {
+            Self::Gzip => &[0x1f, 0x8b],
+            Self::Zstd => &[0x28, 0xb5, 0x2f, 0xfd],
+        }
+    }
+
+    /// Detects the codec of the file by its magic bytes.
+    pub(crate) async fn detect(filename: &::std::path::Path) -> Result<Option<Self>> {
+        let mut file = tokio::fs::File::open(filename).await?;
+
+        let mut magic = [0; 4];
+        let mut len = 0;
+        while len < magic.len() {
+            match file.read(&mut magic[len..]).await? {
+                0 => break,
+                read => len += read,
+            }
+        }
+
+        Ok([Self::Zstd, Self::Gzip]
+            .into_iter()
+            .find(|codec| magic[..len].starts_with(codec.magic())))
+    }
+
+    pub(crate) async fn compress(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        match self {
+            Self::Gzip => GzipEncoder::new(bytes).read_to_end(&mut buf).await?,
+            Self::Zstd => ZstdEncoder::new(bytes).read_to_end(&mut buf).await?,
+        };
+        Ok(buf)
+    }
+
+    /// Decompresses the file chunk by chunk into a private file, up to `limit` bytes.
+    pub(crate) async fn decompress(
+        self,
+        filename: &::std::path::Path,
+        limit: u64,
+        chunk_size: usize,
+    ) -> Result<ModelFile> {
+        let reader = BufReader::new(tokio::fs::File::open(filename).await?);
+        let mut reader: Box<dyn AsyncRead + Send + Unpin> = match self {
+            Self::Gzip => Box::new(GzipDecoder::new(reader)),
+            Self::Zstd => Box::new(ZstdDecoder::new(reader)),
+        };
+
+        // NOTE: the outputs should not be mixed up with the compressed models in the disk cache
+        let name = filename.file_stem().unwrap_or_default().to_string_lossy();
+        let name = format!("{name}.decompressed");
+        let (file, len) =
+            match ModelFile::write_private(&name, &mut reader, limit, chunk_size, |_| ()).await {
+                Ok(written) => written,
+                // NOTE: the corrupted streams would be corrupted again
+                Err(error) => bail!(IpnisError::InvalidModel(anyhow!(
+                    "failed to decompress the model: {error}"
+                ))),
+            };
+
+        // NOTE: the oversized model is removed on drop
+        if len > limit {
+            bail!(IpnisError::InvalidModel(anyhow!(
+                "the decompressed model is too large: expected at most {limit} bytes"
+            )))
+        }
+        Ok(file)
+    }
+}
@@ -1,23 +1,352 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    time::Duration,
+};
+
 use ipis::{async_trait::async_trait, core::anyhow::Result, env::Infer};
-use ipnis_common::onnxruntime::{GraphOptimizationLevel, LoggingLevel};
+use ipnis_common::onnxruntime::{
+    ArenaExtendStrategy, ExecutionMode, GraphOptimizationLevel, LoggingLevel,
+};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// The options of the client.
+///
+/// It can be inferred from the environment with `ClientConfig::try_infer()`,
+/// or made programmatically by overriding the fields of `ClientConfig::default()`.
+#[derive(Clone, Debug, PartialEq)]
 pub struct ClientConfig {
     pub log_level: LoggingLevel,
     pub optimization_level: GraphOptimizationLevel,
+    /// The number of threads parallelizing the execution within each operator.
+    pub intra_op_threads: u8,
+    /// The number of threads parallelizing the execution across the operators.
+    pub inter_op_threads: u8,
+    /// Runs the independent branches of the graph in parallel if `Parallel`.
+    ///
+    /// Note that the parallel mode is pointless with a single inter-op thread.
+    pub execution_mode: ExecutionMode,
+    /// The shared libraries of the custom operators, such as `onnxruntime-extensions`.
+    pub custom_op_libraries: Vec<PathBuf>,
+    /// Preplans the allocations of each session, assuming that the input shapes are stable.
+    ///
+    /// It should be disabled for the models with wildly varying input shapes,
+    /// such as the sequence lengths, as the plans waste the memory.
+    pub enable_mem_pattern: bool,
+    /// Pins the symbolic axes (`dim_param`) with the given sizes, such as `batch = 1`.
+    /// It lets the ONNX Runtime optimize and allocate the sessions statically.
+    pub free_dimension_overrides: HashMap<String, i64>,
+    /// The raw options of the sessions (`AddSessionConfigEntry`),
+    /// such as `session.intra_op.allow_spinning = 0`.
+    ///
+    /// They are passed through as they are, so the unknown keys are rejected by the ONNX Runtime.
+    pub session_config_entries: HashMap<String, String>,
+    /// Produces the bit-identical outputs for the same inputs on the same hardware,
+    /// trading the speed for the reproducibility.
+    ///
+    /// It runs each session sequentially in a single thread, overriding the thread options,
+    /// and makes the ONNX Runtime choose the deterministic kernels.
+    /// Note that some operators and the GPU providers may remain nondeterministic regardless.
+    pub deterministic: bool,
+    /// Overrides `intra_op_threads` unless it is `0`.
+    #[deprecated(note = "use `intra_op_threads` instead")]
     pub number_threads: u8,
+    /// The maximum number of the sessions kept in memory.
+    /// The least recently used sessions are evicted first.
+    pub max_cached_sessions: usize,
+    /// Drops the sessions which have not been used for this duration, if given.
+    /// The idle sessions are found every `sweep_interval` in background.
+    pub idle_timeout: Option<Duration>,
+    pub sweep_interval: Duration,
+    /// How often `watch_alias` resolves the logical names of the models.
+    pub reload_interval: Duration,
+    /// The size of the dynamic axes of the zero-filled inputs, which warm up the sessions.
+    pub warmup_dynamic_size: usize,
+    /// Verifies the downloaded models with the hashes of their paths.
+    /// It can be skipped if the transport is trusted.
+    pub verify_hash: bool,
+    /// Keeps the downloaded models on the local disk across the process restarts, if given.
+    pub cache_dir: Option<PathBuf>,
+    /// The maximum total size of the models kept in `cache_dir`.
+    /// The oldest stored models are evicted first.
+    pub max_cache_dir_bytes: u64,
+    /// Rejects the models larger than this before downloading them, unlimited if `None`.
+    /// The decompressed models are limited as well.
+    pub max_model_bytes: Option<u64>,
+    /// Rejects the compressed models which are decompressed larger than this,
+    /// such as the decompression bombs.
+    pub max_decompressed_bytes: u64,
+    /// The models larger than this are verified chunk by chunk,
+    /// instead of being read into memory at once.
+    pub streaming_threshold: u64,
+    /// Compresses the models stored by `put_model` with the given codec, if given.
+    /// `Zstd` is recommended, as it decompresses much faster than `Gzip`.
+    ///
+    /// The compressed models are detected and decompressed on loading regardless,
+    /// and verified with their paths once decompressed.
+    pub compression: Option<Compression>,
+    /// The size of each chunk read while streaming the models.
+    pub chunk_size: usize,
+    /// The maximum number of the retries when fetching the models has been failed.
+    pub max_retries: u32,
+    /// The delay before the first retry, doubled on each retry.
+    pub base_delay: Duration,
+    pub execution_provider: ExecutionProvider,
+    /// The execution providers tried in order when `execution_provider` fails to initialize,
+    /// only the `Cpu` if empty. It is ignored with `strict_execution_provider`.
+    pub fallback_execution_providers: Vec<ExecutionProvider>,
+    /// The GPUs where the sessions are placed in turn, if not empty.
+    /// It overrides the device of the execution provider.
+    pub device_ids: Vec<i32>,
+    /// The maximum bytes of the GPU memory arena of each session, unlimited if `None`.
+    /// It is only applied to the `Cuda` execution provider.
+    pub gpu_mem_limit: Option<usize>,
+    /// How the GPU memory arena grows when exhausted.
+    pub arena_extend_strategy: ArenaExtendStrategy,
+    /// Overrides the CPU memory arena of each session, the ONNX Runtime's defaults if `None`.
+    pub cpu_arena: Option<ArenaConfig>,
+    /// The memory where the inputs and the outputs of the sessions are allocated.
+    /// The allocators other than `CpuArena` require a GPU execution provider.
+    pub allocator: Allocator,
+    /// Fails instead of falling back to the others when the execution provider is unavailable.
+    pub strict_execution_provider: bool,
+    /// Shrinks the memory arenas of each session after each run,
+    /// returning the memory retained after the bursts to the system.
+    ///
+    /// Note that it slows the next runs down, as the arenas grow again by allocating the chunks.
+    /// The bursty workloads may prefer calling `shrink_memory()` between the bursts instead.
+    pub shrink_memory_after_run: bool,
+    /// Checks the structure and the size of the models before building the sessions, if given.
+    ///
+    /// It bounds the damage of the malformed or adversarial models from the less-trusted sources,
+    /// which may make the protobuf parser of the ONNX Runtime allocate wildly or crash.
+    /// Note that the models are scanned chunk by chunk, seeking over the raw data of the tensors.
+    pub model_guard: Option<ModelGuard>,
+    /// Checks the inputs before running the inference.
+    /// It can be skipped if the inputs are trusted.
+    pub validate_inputs: bool,
+    /// Casts the f32 inputs into f16 if the model declares so, and the f16 outputs into f32.
+    /// It can be disabled to keep the tensor types strict.
+    pub auto_cast_f16: bool,
+    /// The maximum duration of each inference, including loading the model and waiting for the others.
+    /// It can be overridden for each inference with `CallOptions.timeout`.
+    ///
+    /// Note that a fired timeout does not stop the ongoing computation,
+    /// but only abandons waiting on it.
+    pub timeout: Option<Duration>,
+    /// Splits the inputs larger than this into the sub-batches, unlimited if `None`.
+    ///
+    /// The batch axis should be the leading one of all inputs and all outputs,
+    /// as the outputs of the sub-batches are concatenated back along it in order.
+    /// The inputs without the same leading axis are not split.
+    pub max_batch_size: Option<usize>,
+    /// The maximum number of the inferences running at the same time, unlimited if `None`.
+    /// The exceeding inferences wait in queue for the others.
+    ///
+    /// Note that the cancelled inferences release their permits at once,
+    /// while their ongoing computations are finished in background.
+    pub max_concurrent_inferences: Option<usize>,
+    /// The upper bounds of the latency histograms in seconds.
+    #[cfg(feature = "metrics")]
+    pub latency_buckets: Vec<f64>,
+    /// Writes the per-operator timings of each session as a JSON file.
+    pub profiling: bool,
+    /// The directory where the profiles are written.
+    pub profiling_dir: PathBuf,
 }
 
 impl Default for ClientConfig {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
             log_level: LoggingLevel::Warning,
             optimization_level: GraphOptimizationLevel::Basic,
-            number_threads: 1,
+            intra_op_threads: 1,
+            inter_op_threads: 1,
+            execution_mode: ExecutionMode::Sequential,
+            deterministic: false,
+            custom_op_libraries: Default::default(),
+            enable_mem_pattern: true,
+            free_dimension_overrides: Default::default(),
+            session_config_entries: Default::default(),
+            number_threads: 0,
+            max_cached_sessions: 8,
+            reload_interval: Duration::from_secs(60),
+            warmup_dynamic_size: 1,
+            idle_timeout: None,
+            sweep_interval: Duration::from_secs(60),
+            verify_hash: true,
+            cache_dir: None,
+            max_cache_dir_bytes: 16 * 1024 * 1024 * 1024,
+            max_model_bytes: None,
+            max_decompressed_bytes: 16 * 1024 * 1024 * 1024,
+            streaming_threshold: 64 * 1024 * 1024,
+            compression: None,
+            chunk_size: 1024 * 1024,
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+            execution_provider: ExecutionProvider::Cpu,
+            fallback_execution_providers: Default::default(),
+            device_ids: Default::default(),
+            gpu_mem_limit: None,
+            arena_extend_strategy: ArenaExtendStrategy::NextPowerOfTwo,
+            cpu_arena: None,
+            allocator: Allocator::CpuArena,
+            strict_execution_provider: false,
+            shrink_memory_after_run: false,
+            model_guard: None,
+            validate_inputs: true,
+            auto_cast_f16: false,
+            timeout: None,
+            max_batch_size: None,
+            max_concurrent_inferences: None,
+            #[cfg(feature = "metrics")]
+            latency_buckets: vec![
+                0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+            ],
+            profiling: false,
+            profiling_dir: ::std::env::temp_dir(),
+        }
+    }
+}
+
+impl ClientConfig {
+    #[allow(deprecated)]
+    pub(crate) fn intra_op_threads(&self) -> u8 {
+        match self.number_threads {
+            0 => self.intra_op_threads,
+            number_threads => number_threads,
+        }
+    }
+
+    /// Returns the maximum size of the decompressed models.
+    pub(crate) fn max_decompressed_bytes(&self) -> u64 {
+        self.max_model_bytes
+            .map_or(self.max_decompressed_bytes, |max_model_bytes| {
+                max_model_bytes.min(self.max_decompressed_bytes)
+            })
+    }
+}
+
+/// The codec of the compressed models in the storage.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+/// The hardware accelerator which runs the sessions.
+///
+/// The GPU providers require the ONNX Runtime to be built with them,
+/// enabling the `cuda` feature for `Cuda` and the `tensorrt` feature for `TensorRt`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ExecutionProvider {
+    Cpu,
+    Cuda { device_id: i32 },
+    TensorRt { device_id: i32 },
+}
+
+impl ExecutionProvider {
+    /// Moves the GPU providers onto the given device.
+    pub fn with_device_id(self, device_id: i32) -> Self {
+        match self {
+            Self::Cpu => Self::Cpu,
+            Self::Cuda { .. } => Self::Cuda { device_id },
+            Self::TensorRt { .. } => Self::TensorRt { device_id },
+        }
+    }
+}
+
+/// The memory where the inputs and the outputs are allocated.
+///
+/// The transfer-bound models on GPU, such as those with large images, may benefit from `Pinned`,
+/// as the page-locked memory is copied to the device asynchronously with the DMA,
+/// without being staged into a temporary buffer.
+/// `Device` keeps the outputs on the device, which only pays off when they are not read on the host.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Allocator {
+    /// The pageable host memory pooled by the CPU memory arena.
+    CpuArena,
+    /// The page-locked host memory, such as `CUDA_PINNED`.
+    Pinned,
+    /// The memory of the device of the execution provider.
+    Device,
+}
+
+/// The CPU memory arena, which pools the allocations of the sessions.
+///
+/// The arena doubling its chunks (`NextPowerOfTwo`) allocates less frequently,
+/// but may reserve nearly twice the memory of the peak usage.
+/// The memory-constrained deployments may prefer `SameAsRequested` with a small initial chunk,
+/// at the cost of more allocations on the first runs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ArenaConfig {
+    pub extend_strategy: ArenaExtendStrategy,
+    /// The size of the first chunk, the ONNX Runtime's default (1 MiB) if `None`.
+    pub initial_chunk_size: Option<usize>,
+    /// The maximum bytes of the arena, unlimited if `None`.
+    pub max_mem: Option<usize>,
+}
+
+impl Default for ArenaConfig {
+    fn default() -> Self {
+        Self {
+            extend_strategy: ArenaExtendStrategy::NextPowerOfTwo,
+            initial_chunk_size: None,
+            max_mem: None,
+        }
+    }
+}
+
+/// The limits of the models, checked before building the sessions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ModelGuard {
+    /// The maximum number of the nodes, including the ones of the subgraphs.
+    pub max_nodes: usize,
+    /// The maximum total bytes of the initializers (weights), including the ones of the subgraphs.
+    pub max_initializer_bytes: u64,
+}
+
+impl Default for ModelGuard {
+    fn default() -> Self {
+        Self {
+            max_nodes: 1_000_000,
+            // NOTE: the protobuf messages are limited to 2 GiB
+            max_initializer_bytes: 2 * 1024 * 1024 * 1024,
         }
     }
 }
 
+/// The options overriding the client config for each model.
+///
+/// The sessions loaded with the different options are cached separately.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadOptions {
+    /// Overrides `ClientConfig.optimization_level` if given.
+    pub optimization_level: Option<GraphOptimizationLevel>,
+    /// Overrides the device of the execution provider if given.
+    /// The same model can be loaded on the multiple GPUs with the different devices.
+    pub device_id: Option<i32>,
+}
+
+impl Hash for LoadOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.optimization_level
+            .map(|level| level as i32)
+            .hash(state);
+        self.device_id.hash(state);
+    }
+}
+
+/// The options overriding the client config for each inference.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallOptions {
+    /// The options of the session performing the inference.
+    pub load: LoadOptions,
+    /// Overrides `ClientConfig.timeout` if given.
+    pub timeout: Option<Duration>,
+}
+
 #[async_trait]
 impl<'a> Infer<'a> for ClientConfig {
     type GenesisArgs = ();
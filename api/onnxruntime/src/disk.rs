@@ -0,0 +1,82 @@
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use ipis::{core::anyhow::Result, path::Path, tokio};
+
+/// A cache of the downloaded models on the local disk, surviving the process restarts.
+///
+/// The oldest stored models are evicted first when the total size exceeds the capacity.
+///
+/// The cached models are loaded by their filenames without being read into memory,
+/// so serving the large models repeatedly does not raise the peak memory.
+pub(crate) struct DiskCache<'a> {
+    dir: &'a ::std::path::Path,
+    capacity: u64,
+}
+
+impl<'a> DiskCache<'a> {
+    pub(crate) fn new(dir: &'a ::std::path::Path, capacity: u64) -> Self {
+        Self { dir, capacity }
+    }
+
+    pub(crate) fn filename(&self, path: &Path) -> PathBuf {
+        self.dir.join(format!("{}.onnx", path.value))
+    }
+
+    /// Copies the verified model into the cache, returning the cached filename.
+    pub(crate) async fn store(&self, path: &Path, source: &::std::path::Path) -> Result<PathBuf> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        tokio::fs::create_dir_all(self.dir).await?;
+
+        // NOTE: the model is renamed atomically, so the other processes never see a partial one
+        let filename = self.filename(path);
+        let temp = self.dir.join(format!(
+            "{}.onnx.{}.{}.tmp",
+            path.value,
+            ::std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+        ));
+        if let Err(error) = tokio::fs::copy(source, &temp).await {
+            tokio::fs::remove_file(&temp).await.ok();
+            return Err(error.into());
+        }
+        tokio::fs::rename(&temp, &filename).await?;
+
+        self.evict(&filename).await?;
+        Ok(filename)
+    }
+
+    async fn evict(&self, keep: &::std::path::Path) -> Result<()> {
+        let mut entries = Vec::new();
+        let mut dir = tokio::fs::read_dir(self.dir).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let filename = entry.path();
+            if filename.extension().and_then(|e| e.to_str()) != Some("onnx") {
+                continue;
+            }
+
+            let metadata = entry.metadata().await?;
+            entries.push((metadata.modified()?, metadata.len(), filename));
+        }
+
+        // evict the oldest models
+        entries.sort_unstable();
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        for (_, len, filename) in entries {
+            if total <= self.capacity {
+                break;
+            }
+            if filename == keep {
+                continue;
+            }
+
+            // NOTE: the model may have been already evicted by the other processes
+            tokio::fs::remove_file(&filename).await.ok();
+            total -= len;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use ipis::{
+    core::anyhow::Result,
+    tokio::{
+        self,
+        io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    },
+};
+
+/// The local file of a fetched model, to be built into a session.
+///
+/// The private files are made for each load, such as the decompressed models,
+/// and removed on drop, once the session has been built from them.
+#[derive(Debug)]
+pub(crate) struct ModelFile {
+    filename: PathBuf,
+    /// The private file or directory to be removed on drop, if any.
+    owned: Option<PathBuf>,
+}
+
+impl AsRef<::std::path::Path> for ModelFile {
+    fn as_ref(&self) -> &::std::path::Path {
+        &self.filename
+    }
+}
+
+impl Drop for ModelFile {
+    fn drop(&mut self) {
+        // NOTE: the private files may have been already removed by the others
+        if let Some(owned) = self.owned.take() {
+            if owned.is_dir() {
+                ::std::fs::remove_dir_all(owned).ok();
+            } else {
+                ::std::fs::remove_file(owned).ok();
+            }
+        }
+    }
+}
+
+impl ModelFile {
+    /// Refers to the file which is owned by the others, such as the storage or the disk cache.
+    pub(crate) fn shared(filename: PathBuf) -> Self {
+        Self {
+            filename,
+            owned: None,
+        }
+    }
+
+    /// Unpacks the tarball into a private directory, referring to the given file in it.
+    ///
+    /// NOTE: the entries escaping the directory, such as `../model.onnx`, are rejected by `tar`
+    pub(crate) async fn unpack_private(
+        name: &str,
+        tarball: &::std::path::Path,
+        root: &str,
+    ) -> Result<Self> {
+        let dirname = unique_filename(name);
+
+        // NOTE: the partial directory is removed on drop, whether succeeded or not
+        let file = Self {
+            filename: dirname.join(root),
+            owned: Some(dirname.clone()),
+        };
+        let tarball = tarball.to_path_buf();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            ::tar::Archive::new(::std::fs::File::open(tarball)?).unpack(dirname)?;
+            Ok(())
+        })
+        .await??;
+        Ok(file)
+    }
+
+    /// Copies the reader into a private file, up to `limit` bytes,
+    /// returning the file and the number of the bytes read.
+    ///
+    /// The file is written with a unique temporary name, and renamed into place once finished,
+    /// so the concurrent loads never share nor see the partial files.
+    /// Note that the returned length exceeds `limit` if the reader is longer,
+    /// so the callers should check it.
+    pub(crate) async fn write_private<R, F>(
+        name: &str,
+        reader: R,
+        limit: u64,
+        chunk_size: usize,
+        mut inspect: F,
+    ) -> Result<(Self, u64)>
+    where
+        R: AsyncRead + Unpin,
+        F: FnMut(&[u8]),
+    {
+        let filename = unique_filename(name).with_extension("onnx");
+
+        // NOTE: the partial file is removed on drop, whether succeeded or not
+        let mut partial = Self::private(filename.with_extension("onnx.tmp"));
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&partial.filename)
+            .await?;
+
+        // stop reading just after the limit
+        let mut reader = reader.take(limit.saturating_add(1));
+        let mut chunk = vec![0; chunk_size.max(1)];
+        let mut len = 0u64;
+        loop {
+            let read = reader.read(&mut chunk).await?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&chunk[..read]).await?;
+            len += read as u64;
+            inspect(&chunk[..read]);
+        }
+        file.flush().await?;
+        drop(file);
+
+        tokio::fs::rename(&partial.filename, &filename).await?;
+        partial.owned = None;
+        Ok((Self::private(filename), len))
+    }
+
+    fn private(filename: PathBuf) -> Self {
+        Self {
+            owned: Some(filename.clone()),
+            filename,
+        }
+    }
+}
+
+/// Returns a unique filename in the temporary directory, which is not created yet.
+fn unique_filename(name: &str) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    ::std::env::temp_dir().join(format!(
+        "ipnis-{name}.{}.{}",
+        ::std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed),
+    ))
+}
@@ -0,0 +1,537 @@
+use std::{
+    collections::BTreeSet,
+    io::{BufReader, Cursor, Read, Seek},
+};
+
+use ipis::core::anyhow::{bail, Result};
+
+use crate::config::ModelGuard;
+
+/// The fields of the ONNX protobuf messages, which are visited by the scanner.
+const MODEL_IR_VERSION: u64 = 1;
+const MODEL_GRAPH: u64 = 7;
+const GRAPH_NODE: u64 = 1;
+const GRAPH_INITIALIZER: u64 = 5;
+const GRAPH_INPUT: u64 = 11;
+const GRAPH_OUTPUT: u64 = 12;
+const GRAPH_SPARSE_INITIALIZER: u64 = 15;
+const NODE_OP_TYPE: u64 = 4;
+const NODE_ATTRIBUTE: u64 = 5;
+const ATTRIBUTE_TENSOR: u64 = 5;
+const ATTRIBUTE_GRAPH: u64 = 6;
+const ATTRIBUTE_TENSORS: u64 = 10;
+const ATTRIBUTE_GRAPHS: u64 = 11;
+const ATTRIBUTE_SPARSE_TENSOR: u64 = 22;
+const ATTRIBUTE_SPARSE_TENSORS: u64 = 23;
+const TENSOR_DATA_LOCATION: u64 = 14;
+const SPARSE_TENSOR_VALUES: u64 = 1;
+const SPARSE_TENSOR_INDICES: u64 = 2;
+const VALUE_INFO_TYPE: u64 = 2;
+const TYPE_TENSOR: u64 = 1;
+const TYPE_SPARSE_TENSOR: u64 = 8;
+const TENSOR_TYPE_SHAPE: u64 = 2;
+const SHAPE_DIM: u64 = 1;
+const DIMENSION_PARAM: u64 = 2;
+
+/// The operator types which are only used by the quantized models.
+const QUANTIZED_OPS: &[&[u8]] = &[
+    b"QLinearConv",
+    b"QLinearMatMul",
+    b"QuantizeLinear",
+    b"DequantizeLinear",
+    b"DynamicQuantizeLinear",
+    b"MatMulInteger",
+    b"ConvInteger",
+];
+
+/// The value of `TensorProto.data_location` of the tensors stored in the separate files.
+const DATA_LOCATION_EXTERNAL: u64 = 1;
+
+/// The maximum depth of the nested subgraphs, such as the bodies of `If` and `Loop`.
+const MAX_DEPTH: usize = 64;
+
+/// The maximum length of the symbolic axes to be collected.
+const MAX_DIMENSION_LEN: usize = 4096;
+
+/// The facts of the model, found by scanning its structure.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct ModelScan {
+    /// Whether any node is a quantized operator, such as `QLinearConv`.
+    pub(crate) quantized: bool,
+    /// Whether any tensor refers to the external data (`data_location = EXTERNAL`).
+    pub(crate) external_data: bool,
+    /// The symbolic axes (`dim_param`) of the inputs and the outputs of the main graph.
+    pub(crate) symbolic_dimensions: BTreeSet<String>,
+}
+
+/// Scans the model in memory, checking it with the guard if given.
+///
+/// Only the wire format of the messages is parsed, so the contents of the nodes
+/// and the initializers are still checked by the ONNX Runtime.
+pub(crate) fn scan(model_bytes: &[u8], guard: Option<&ModelGuard>) -> Result<ModelScan> {
+    scan_reader(Cursor::new(model_bytes), model_bytes.len() as u64, guard)
+}
+
+/// Scans the model file, checking it with the guard if given.
+///
+/// Only the messages of the graphs, the nodes and the tensors are visited,
+/// seeking over the others such as the raw data of the tensors, so the model is never read at once.
+///
+/// NOTE: it blocks the thread while reading the file
+pub(crate) fn scan_file(
+    filename: &::std::path::Path,
+    guard: Option<&ModelGuard>,
+) -> Result<ModelScan> {
+    let file = ::std::fs::File::open(filename)?;
+    let len = file.metadata()?.len();
+    scan_reader(file, len, guard)
+}
+
+fn scan_reader<R>(reader: R, len: u64, guard: Option<&ModelGuard>) -> Result<ModelScan>
+where
+    R: Read + Seek,
+{
+    let mut scanner = Scanner {
+        reader: BufReader::new(reader),
+        position: 0,
+    };
+    let mut scan = Scan {
+        guard,
+        nodes: 0,
+        initializer_bytes: 0,
+        facts: Default::default(),
+    };
+
+    let mut has_ir_version = false;
+    let mut has_graph = false;
+    scanner.walk(len, |scanner, field, value| {
+        match (field, value) {
+            (MODEL_IR_VERSION, Value::Varint(_)) => has_ir_version = true,
+            (MODEL_GRAPH, Value::Bytes { end }) => {
+                has_graph = true;
+                scan.graph(scanner, end, 0)?;
+            }
+            (MODEL_IR_VERSION | MODEL_GRAPH, _) => {
+                bail!("malformed ONNX model: unexpected wire type of the field {field}")
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+
+    if !has_ir_version {
+        bail!("malformed ONNX model: missing ir_version")
+    }
+    if !has_graph {
+        bail!("malformed ONNX model: missing graph")
+    }
+    Ok(scan.facts)
+}
+
+/// The resources of the model counted so far, across the subgraphs.
+struct Scan<'a> {
+    guard: Option<&'a ModelGuard>,
+    nodes: usize,
+    initializer_bytes: u64,
+    facts: ModelScan,
+}
+
+impl Scan<'_> {
+    fn graph<R>(&mut self, scanner: &mut Scanner<R>, end: u64, depth: usize) -> Result<()>
+    where
+        R: Read + Seek,
+    {
+        if depth > MAX_DEPTH {
+            bail!("the model is nested too deeply: expected at most {MAX_DEPTH} levels")
+        }
+
+        scanner.walk(end, |scanner, field, value| match (field, value) {
+            (GRAPH_NODE, Value::Bytes { end }) => self.node(scanner, end, depth),
+            (GRAPH_INPUT | GRAPH_OUTPUT, Value::Bytes { end }) if depth == 0 => {
+                self.value_info(scanner, end)
+            }
+            (GRAPH_INITIALIZER, Value::Bytes { end }) => {
+                self.initializer(end - scanner.position)?;
+                self.tensor(scanner, end)
+            }
+            (GRAPH_SPARSE_INITIALIZER, Value::Bytes { end }) => {
+                self.initializer(end - scanner.position)?;
+                self.sparse_tensor(scanner, end)
+            }
+            (GRAPH_NODE | GRAPH_INITIALIZER | GRAPH_SPARSE_INITIALIZER, _) => {
+                bail!("malformed ONNX model: unexpected wire type of the field {field}")
+            }
+            _ => Ok(()),
+        })
+    }
+
+    fn node<R>(&mut self, scanner: &mut Scanner<R>, end: u64, depth: usize) -> Result<()>
+    where
+        R: Read + Seek,
+    {
+        self.nodes += 1;
+        if let Some(guard) = self.guard {
+            if self.nodes > guard.max_nodes {
+                let max_nodes = guard.max_nodes;
+                bail!("the model has too many nodes: expected at most {max_nodes}")
+            }
+        }
+
+        // find the subgraphs and the tensors in the attributes
+        scanner.walk(end, |scanner, field, value| match (field, value) {
+            (NODE_OP_TYPE, Value::Bytes { end }) => {
+                // NOTE: the longer operator types are not read, as they cannot be quantized ones
+                let max_len = QUANTIZED_OPS
+                    .iter()
+                    .map(|op| op.len())
+                    .max()
+                    .unwrap_or_default();
+                if let Some(op_type) = scanner.read_short(end, max_len)? {
+                    self.facts.quantized |= QUANTIZED_OPS.contains(&op_type.as_slice());
+                }
+                Ok(())
+            }
+            (NODE_ATTRIBUTE, Value::Bytes { end }) => {
+                scanner.walk(end, |scanner, field, value| match (field, value) {
+                    (ATTRIBUTE_GRAPH | ATTRIBUTE_GRAPHS, Value::Bytes { end }) => {
+                        self.graph(scanner, end, depth + 1)
+                    }
+                    (ATTRIBUTE_TENSOR | ATTRIBUTE_TENSORS, Value::Bytes { end }) => {
+                        self.tensor(scanner, end)
+                    }
+                    (ATTRIBUTE_SPARSE_TENSOR | ATTRIBUTE_SPARSE_TENSORS, Value::Bytes { end }) => {
+                        self.sparse_tensor(scanner, end)
+                    }
+                    _ => Ok(()),
+                })
+            }
+            _ => Ok(()),
+        })
+    }
+
+    /// Collects the symbolic axes of the tensors.
+    fn value_info<R>(&mut self, scanner: &mut Scanner<R>, end: u64) -> Result<()>
+    where
+        R: Read + Seek,
+    {
+        // ValueInfoProto.type.(tensor_type | sparse_tensor_type).shape.dim
+        const DIMENSIONS: &[&[u64]] = &[
+            &[VALUE_INFO_TYPE],
+            &[TYPE_TENSOR, TYPE_SPARSE_TENSOR],
+            &[TENSOR_TYPE_SHAPE],
+            &[SHAPE_DIM],
+        ];
+
+        let dimensions = &mut self.facts.symbolic_dimensions;
+        let mut collect = |scanner: &mut Scanner<R>, field: u64, value: Value| -> Result<()> {
+            if let (DIMENSION_PARAM, Value::Bytes { end }) = (field, value) {
+                if let Some(name) = scanner.read_short(end, MAX_DIMENSION_LEN)? {
+                    dimensions.insert(String::from_utf8_lossy(&name).into_owned());
+                }
+            }
+            Ok(())
+        };
+        scanner.walk_path(end, DIMENSIONS, &mut collect)
+    }
+
+    fn initializer(&mut self, len: u64) -> Result<()> {
+        self.initializer_bytes += len;
+        if let Some(guard) = self.guard {
+            if self.initializer_bytes > guard.max_initializer_bytes {
+                let max_initializer_bytes = guard.max_initializer_bytes;
+                bail!("the initializers are too large: expected at most {max_initializer_bytes} bytes")
+            }
+        }
+        Ok(())
+    }
+
+    fn tensor<R>(&mut self, scanner: &mut Scanner<R>, end: u64) -> Result<()>
+    where
+        R: Read + Seek,
+    {
+        scanner.walk(end, |_, field, value| {
+            if let (TENSOR_DATA_LOCATION, Value::Varint(DATA_LOCATION_EXTERNAL)) = (field, value) {
+                self.facts.external_data = true;
+            }
+            Ok(())
+        })
+    }
+
+    fn sparse_tensor<R>(&mut self, scanner: &mut Scanner<R>, end: u64) -> Result<()>
+    where
+        R: Read + Seek,
+    {
+        scanner.walk(end, |scanner, field, value| match (field, value) {
+            (SPARSE_TENSOR_VALUES | SPARSE_TENSOR_INDICES, Value::Bytes { end }) => {
+                self.tensor(scanner, end)
+            }
+            _ => Ok(()),
+        })
+    }
+}
+
+/// The value of a protobuf field, by its wire type.
+enum Value {
+    Varint(u64),
+    Fixed,
+    /// The contents until `end`, which are skipped unless read by the visitor.
+    Bytes {
+        end: u64,
+    },
+}
+
+/// A reader of the protobuf messages, which tracks its position to bound the nested ones.
+struct Scanner<R> {
+    reader: BufReader<R>,
+    position: u64,
+}
+
+impl<R> Scanner<R>
+where
+    R: Read + Seek,
+{
+    /// Visits the fields of a protobuf message in order until `end`, rejecting the malformed ones.
+    fn walk<F>(&mut self, end: u64, mut f: F) -> Result<()>
+    where
+        F: FnMut(&mut Self, u64, Value) -> Result<()>,
+    {
+        while self.position < end {
+            let key = self.read_varint(end)?;
+            let (field, wire_type) = (key >> 3, key & 0x7);
+            if field == 0 {
+                bail!("malformed protobuf: the field number should not be 0")
+            }
+
+            match wire_type {
+                0 => {
+                    let value = self.read_varint(end)?;
+                    f(self, field, Value::Varint(value))?;
+                }
+                1 => {
+                    self.skip(8, end, field)?;
+                    f(self, field, Value::Fixed)?;
+                }
+                2 => {
+                    let len = self.read_varint(end)?;
+                    self.check_bounds(len, end, field)?;
+                    let field_end = self.position + len;
+                    f(self, field, Value::Bytes { end: field_end })?;
+
+                    // skip the rest of the contents
+                    // NOTE: the visitor never reads beyond the end, as bounded by `walk`
+                    self.skip(field_end - self.position, field_end, field)?;
+                }
+                5 => {
+                    self.skip(4, end, field)?;
+                    f(self, field, Value::Fixed)?;
+                }
+                // NOTE: the groups (3 and 4) are deprecated, and not used by ONNX
+                _ => {
+                    bail!(
+                        "malformed protobuf: unsupported wire type {wire_type} of the field {field}"
+                    )
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Visits the fields of the nested messages along the path, until `end`.
+    ///
+    /// Each step of the path matches any of the given field numbers.
+    fn walk_path<F>(&mut self, end: u64, path: &[&[u64]], f: &mut F) -> Result<()>
+    where
+        F: FnMut(&mut Self, u64, Value) -> Result<()>,
+    {
+        match path.split_first() {
+            Some((fields, path)) => self.walk(end, |scanner, field, value| match value {
+                Value::Bytes { end } if fields.contains(&field) => scanner.walk_path(end, path, f),
+                _ => Ok(()),
+            }),
+            None => self.walk(end, f),
+        }
+    }
+
+    fn read_varint(&mut self, end: u64) -> Result<u64> {
+        let mut value = 0u64;
+        for index in 0..10 {
+            if self.position >= end {
+                break;
+            }
+            let mut byte = [0];
+            self.reader.read_exact(&mut byte)?;
+            self.position += 1;
+
+            value |= u64::from(byte[0] & 0x7f) << (7 * index);
+            if byte[0] & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("malformed protobuf: truncated or overlong varint")
+    }
+
+    /// Reads the contents until `end`, unless longer than `max_len`.
+    fn read_short(&mut self, end: u64, max_len: usize) -> Result<Option<Vec<u8>>> {
+        let len = end - self.position;
+        if len > max_len as u64 {
+            return Ok(None);
+        }
+
+        let mut bytes = vec![0; len as usize];
+        self.reader.read_exact(&mut bytes)?;
+        self.position = end;
+        Ok(Some(bytes))
+    }
+
+    fn skip(&mut self, len: u64, end: u64, field: u64) -> Result<()> {
+        self.check_bounds(len, end, field)?;
+        if len > 0 {
+            // NOTE: the short skips are served from the buffer
+            self.reader.seek_relative(len.try_into()?)?;
+            self.position += len;
+        }
+        Ok(())
+    }
+
+    fn check_bounds(&self, len: u64, end: u64, field: u64) -> Result<()> {
+        let given = end - self.position;
+        if len > given {
+            bail!("malformed protobuf: the field {field} overflows the message: expected {len} bytes, but given {given}")
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{node, relu, tensor, value_info, Dim, Graph, Message, FLOAT};
+
+    #[test]
+    fn test_scan() {
+        let scan = scan(&relu().into_model(), Some(&Default::default())).unwrap();
+
+        assert!(!scan.quantized);
+        assert!(!scan.external_data);
+        assert_eq!(
+            scan.symbolic_dimensions,
+            BTreeSet::from(["batch".to_string()]),
+        );
+    }
+
+    #[test]
+    fn test_scan_file() {
+        let model_bytes = relu().into_model();
+        let filename =
+            ::std::env::temp_dir().join(format!("ipnis-test-guard-{}.onnx", ::std::process::id()));
+        ::std::fs::write(&filename, &model_bytes).unwrap();
+
+        let scanned = scan_file(&filename, None);
+        ::std::fs::remove_file(&filename).unwrap();
+        assert_eq!(scanned.unwrap(), scan(&model_bytes, None).unwrap());
+    }
+
+    #[test]
+    fn test_scan_quantized() {
+        let graph = relu().node(node("QuantizeLinear", &["y", "s"], &["z"]));
+        assert!(scan(&graph.into_model(), None).unwrap().quantized);
+
+        // the longer operator types are not quantized ones
+        let graph = relu().node(node("QuantizeLinearButCustom", &["y"], &["z"]));
+        assert!(!scan(&graph.into_model(), None).unwrap().quantized);
+    }
+
+    #[test]
+    fn test_scan_subgraphs() {
+        // If(then_branch = QLinearConv)
+        let branch = Graph::default()
+            .node(node("QLinearConv", &[], &["z"]))
+            .output(value_info("z", FLOAT, &[Dim::Param("inner")]));
+        let attribute = Message::default()
+            .bytes(1, "then_branch")
+            .message(ATTRIBUTE_GRAPH, branch.into_message());
+        let graph = relu().node(node("If", &["c"], &["z"]).message(NODE_ATTRIBUTE, attribute));
+
+        let scan = scan(&graph.into_model(), None).unwrap();
+        assert!(scan.quantized);
+        // the axes of the subgraphs are not the ones of the model
+        assert_eq!(
+            scan.symbolic_dimensions,
+            BTreeSet::from(["batch".to_string()]),
+        );
+    }
+
+    #[test]
+    fn test_scan_external_data() {
+        let external = tensor("w", &[2], &[]).varint(TENSOR_DATA_LOCATION, DATA_LOCATION_EXTERNAL);
+        let graph = relu().initializer(external);
+        assert!(scan(&graph.into_model(), None).unwrap().external_data);
+
+        // the names of the tensors are not the locations
+        let graph = relu()
+            .initializer(tensor("location", &[1], &[1.0]))
+            .input(value_info("location", FLOAT, &[Dim::Value(1)]));
+        assert!(!scan(&graph.into_model(), None).unwrap().external_data);
+    }
+
+    #[test]
+    fn test_scan_guard() {
+        let graph = relu()
+            .node(node("Relu", &["y"], &["z"]))
+            .initializer(tensor("w", &[4], &[1.0, 2.0, 3.0, 4.0]));
+        let model_bytes = graph.into_model();
+
+        let guard = ModelGuard {
+            max_nodes: 1,
+            ..Default::default()
+        };
+        assert!(scan(&model_bytes, Some(&guard)).is_err());
+
+        let guard = ModelGuard {
+            max_initializer_bytes: 16,
+            ..Default::default()
+        };
+        assert!(scan(&model_bytes, Some(&guard)).is_err());
+
+        // the guard is optional
+        assert!(scan(&model_bytes, None).is_ok());
+    }
+
+    #[test]
+    fn test_scan_nested_too_deeply() {
+        let graph = (0..=MAX_DEPTH).fold(relu(), |graph, _| {
+            let attribute = Message::default()
+                .bytes(1, "body")
+                .message(ATTRIBUTE_GRAPH, graph.into_message());
+            Graph::default().node(node("Loop", &[], &[]).message(NODE_ATTRIBUTE, attribute))
+        });
+        assert!(scan(&graph.into_model(), None).is_err());
+    }
+
+    #[test]
+    fn test_scan_malformed() {
+        let model_bytes = relu().into_model();
+
+        // truncated
+        assert!(scan(&model_bytes[..model_bytes.len() - 1], None).is_err());
+
+        // missing fields
+        let missing_graph = Message::default().varint(MODEL_IR_VERSION, 7).into_bytes();
+        assert!(scan(&missing_graph, None).is_err());
+        let missing_ir_version = Message::default()
+            .message(MODEL_GRAPH, relu().into_message())
+            .into_bytes();
+        assert!(scan(&missing_ir_version, None).is_err());
+
+        // unexpected wire types
+        let graph_as_varint = Message::default()
+            .varint(MODEL_IR_VERSION, 7)
+            .varint(MODEL_GRAPH, 1)
+            .into_bytes();
+        assert!(scan(&graph_as_varint, None).is_err());
+
+        // not an ONNX model
+        assert!(scan(b"\xff\xff\xff\xff", None).is_err());
+        assert!(scan(b"", None).is_err());
+    }
+}
@@ -1,2 +1,13 @@
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod cache;
 pub mod client;
+mod compression;
 pub mod config;
+mod disk;
+mod file;
+mod guard;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(test)]
+mod testing;
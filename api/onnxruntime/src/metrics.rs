@@ -0,0 +1,86 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+use ipis::path::Path;
+
+/// The latency histograms of the inferences per model.
+pub(crate) struct LatencyMetrics {
+    /// The upper bounds of the buckets in seconds, in ascending order.
+    buckets: Vec<f64>,
+    models: Mutex<HashMap<Path, Histogram>>,
+}
+
+struct Histogram {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl LatencyMetrics {
+    pub(crate) fn new(mut buckets: Vec<f64>) -> Self {
+        buckets.sort_by(f64::total_cmp);
+        buckets.dedup();
+
+        Self {
+            buckets,
+            models: Default::default(),
+        }
+    }
+
+    pub(crate) fn observe(&self, path: &Path, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+
+        let mut models = self.models.lock().unwrap_or_else(PoisonError::into_inner);
+        let histogram = models.entry(*path).or_insert_with(|| Histogram {
+            counts: vec![0; self.buckets.len()],
+            sum: 0.0,
+            count: 0,
+        });
+
+        for (count, _) in histogram
+            .counts
+            .iter_mut()
+            .zip(&self.buckets)
+            .filter(|(_, bound)| seconds <= **bound)
+        {
+            *count += 1;
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+    }
+
+    /// Renders the histograms in the Prometheus text exposition format.
+    pub(crate) fn render(&self) -> String {
+        const NAME: &str = "ipnis_inference_duration_seconds";
+
+        let mut output = String::new();
+        writeln!(output, "# HELP {NAME} The latency of the inferences.").ok();
+        writeln!(output, "# TYPE {NAME} histogram").ok();
+
+        let models = self.models.lock().unwrap_or_else(PoisonError::into_inner);
+        for (path, histogram) in models.iter() {
+            let model = path.value;
+            for (count, bound) in histogram.counts.iter().zip(&self.buckets) {
+                writeln!(
+                    output,
+                    "{NAME}_bucket{{model=\"{model}\",le=\"{bound}\"}} {count}"
+                )
+                .ok();
+            }
+            let count = histogram.count;
+            let sum = histogram.sum;
+            writeln!(
+                output,
+                "{NAME}_bucket{{model=\"{model}\",le=\"+Inf\"}} {count}"
+            )
+            .ok();
+            writeln!(output, "{NAME}_sum{{model=\"{model}\"}} {sum}").ok();
+            writeln!(output, "{NAME}_count{{model=\"{model}\"}} {count}").ok();
+        }
+        output
+    }
+}
@@ -0,0 +1,249 @@
+//! The fixtures of the tests, such as the tiny ONNX models encoded by hand.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use ipis::{
+    async_trait::async_trait,
+    core::{
+        anyhow::{anyhow, Result},
+        value::hash::Hash,
+    },
+    path::Path,
+    tokio::{
+        self,
+        io::{AsyncRead, AsyncReadExt},
+    },
+};
+use ipsis_common::Ipsis;
+
+/// The element types of the tensors (`TensorProto.DataType`).
+pub(crate) const FLOAT: u64 = 1;
+pub(crate) const INT64: u64 = 7;
+pub(crate) const BOOL: u64 = 9;
+
+/// The versions of the encoded models, supported by the ONNX Runtime.
+const IR_VERSION: u64 = 7;
+const OPSET_VERSION: u64 = 13;
+
+/// An encoder of the protobuf messages, appending the fields in order.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Message(Vec<u8>);
+
+impl Message {
+    pub(crate) fn varint(mut self, field: u64, value: u64) -> Self {
+        self.push_key(field, 0);
+        self.push_varint(value);
+        self
+    }
+
+    pub(crate) fn bytes(mut self, field: u64, value: impl AsRef<[u8]>) -> Self {
+        let value = value.as_ref();
+        self.push_key(field, 2);
+        self.push_varint(value.len() as u64);
+        self.0.extend_from_slice(value);
+        self
+    }
+
+    pub(crate) fn message(self, field: u64, value: Self) -> Self {
+        self.bytes(field, value.0)
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    fn push_key(&mut self, field: u64, wire_type: u64) {
+        self.push_varint((field << 3) | wire_type);
+    }
+
+    fn push_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+}
+
+/// An axis of the inputs and the outputs.
+pub(crate) enum Dim {
+    Value(u64),
+    Param(&'static str),
+}
+
+/// Encodes a `ValueInfoProto` of the tensor.
+pub(crate) fn value_info(name: &str, elem_type: u64, dims: &[Dim]) -> Message {
+    let shape = dims.iter().fold(Message::default(), |shape, dim| {
+        let dim = match dim {
+            Dim::Value(value) => Message::default().varint(1, *value),
+            Dim::Param(param) => Message::default().bytes(2, param),
+        };
+        shape.message(1, dim)
+    });
+    let tensor_type = Message::default().varint(1, elem_type).message(2, shape);
+
+    Message::default()
+        .bytes(1, name)
+        .message(2, Message::default().message(1, tensor_type))
+}
+
+/// Encodes a `NodeProto` of the default domain.
+pub(crate) fn node(op_type: &str, inputs: &[&str], outputs: &[&str]) -> Message {
+    let node = inputs
+        .iter()
+        .fold(Message::default(), |node, input| node.bytes(1, input));
+    outputs
+        .iter()
+        .fold(node, |node, output| node.bytes(2, output))
+        .bytes(4, op_type)
+}
+
+/// Encodes a `TensorProto` of the floats, stored as the raw data.
+pub(crate) fn tensor(name: &str, dims: &[u64], values: &[f32]) -> Message {
+    let raw_data: Vec<u8> = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+
+    dims.iter()
+        .fold(Message::default(), |tensor, dim| tensor.varint(1, *dim))
+        .varint(2, FLOAT)
+        .bytes(8, name)
+        .bytes(9, raw_data)
+}
+
+/// An encoder of the `GraphProto`, to be wrapped into a model.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Graph(Message);
+
+impl Graph {
+    pub(crate) fn node(self, node: Message) -> Self {
+        Self(self.0.message(1, node))
+    }
+
+    pub(crate) fn initializer(self, tensor: Message) -> Self {
+        Self(self.0.message(5, tensor))
+    }
+
+    pub(crate) fn input(self, value_info: Message) -> Self {
+        Self(self.0.message(11, value_info))
+    }
+
+    pub(crate) fn output(self, value_info: Message) -> Self {
+        Self(self.0.message(12, value_info))
+    }
+
+    pub(crate) fn into_message(self) -> Message {
+        self.0.bytes(2, "test")
+    }
+
+    /// Encodes the `ModelProto` of the graph, importing the default opset.
+    pub(crate) fn into_model(self) -> Vec<u8> {
+        let opset = Message::default().bytes(1, "").varint(2, OPSET_VERSION);
+
+        Message::default()
+            .varint(1, IR_VERSION)
+            .message(8, opset)
+            .message(7, self.into_message())
+            .into_bytes()
+    }
+}
+
+/// Encodes the model of `y = Relu(x)`, whose leading axis is symbolic (`batch`).
+pub(crate) fn relu() -> Graph {
+    Graph::default()
+        .node(node("Relu", &["x"], &["y"]))
+        .input(value_info(
+            "x",
+            FLOAT,
+            &[Dim::Param("batch"), Dim::Value(2)],
+        ))
+        .output(value_info(
+            "y",
+            FLOAT,
+            &[Dim::Param("batch"), Dim::Value(2)],
+        ))
+}
+
+/// Returns the path of the object in the storage.
+pub(crate) fn path_of(bytes: &[u8]) -> Path {
+    Path {
+        value: Hash::with_bytes(bytes),
+        len: bytes.len() as u64,
+    }
+}
+
+/// Runs the future to completion on a new multi-threaded runtime.
+pub(crate) fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// A storage keeping the objects in memory, counting the fetches.
+#[derive(Default)]
+pub(crate) struct MockIpsis {
+    objects: ::std::sync::Mutex<HashMap<Path, Vec<u8>>>,
+    /// The number of the fetched objects, including the missing ones.
+    num_gets: AtomicUsize,
+}
+
+impl MockIpsis {
+    /// Stores the object, returning its path.
+    pub(crate) fn insert(&self, bytes: Vec<u8>) -> Path {
+        let path = path_of(&bytes);
+        self.objects.lock().unwrap().insert(path, bytes);
+        path
+    }
+
+    pub(crate) fn num_gets(&self) -> usize {
+        self.num_gets.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl Ipsis for MockIpsis {
+    type Reader = ::std::io::Cursor<Vec<u8>>;
+
+    async fn protocol(&self) -> Result<String> {
+        Ok("mock".into())
+    }
+
+    async fn get_raw(&self, path: &Path) -> Result<Self::Reader> {
+        self.num_gets.fetch_add(1, Ordering::SeqCst);
+
+        // NOTE: the missing objects are reported as the other failures, as the real backends do
+        match self.objects.lock().unwrap().get(path) {
+            Some(bytes) => Ok(::std::io::Cursor::new(bytes.clone())),
+            None => Err(anyhow!("failed to open the object: {}", path.value)),
+        }
+    }
+
+    async fn put_raw<R>(&self, path: &Path, mut data: R) -> Result<()>
+    where
+        R: AsyncRead + Send + Sync + Unpin + 'static,
+    {
+        let mut bytes = Vec::new();
+        data.read_to_end(&mut bytes).await?;
+        self.objects.lock().unwrap().insert(*path, bytes);
+        Ok(())
+    }
+
+    async fn contains(&self, path: &Path) -> Result<bool> {
+        Ok(self.objects.lock().unwrap().contains_key(path))
+    }
+
+    async fn delete(&self, path: &Path) -> Result<()> {
+        self.objects.lock().unwrap().remove(path);
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 pub extern crate ipnis_common as common;
 
+pub mod remote;
 pub mod server;
 
 #[cfg(feature = "onnxruntime")]
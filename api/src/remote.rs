@@ -0,0 +1,79 @@
+use ipis::{async_trait::async_trait, core::anyhow::Result, path::Path};
+use ipnis_common::{error::IpnisError, model::Model, tensor::Tensor, Ipnis};
+
+/// The client forwarding the inferences to the remote `IpnisServer` over ipiis.
+pub type IpnisRemoteClient = ::ipiis_api::client::IpiisClient;
+
+/// The client performing the inferences either locally or remotely.
+pub enum IpnisAnyClient {
+    #[cfg(feature = "onnxruntime")]
+    Local(::ipnis_api_onnxruntime::client::IpnisClient),
+    Remote(IpnisRemoteClient),
+}
+
+#[cfg(feature = "onnxruntime")]
+impl From<::ipnis_api_onnxruntime::client::IpnisClient> for IpnisAnyClient {
+    fn from(client: ::ipnis_api_onnxruntime::client::IpnisClient) -> Self {
+        Self::Local(client)
+    }
+}
+
+impl From<IpnisRemoteClient> for IpnisAnyClient {
+    fn from(client: IpnisRemoteClient) -> Self {
+        Self::Remote(client)
+    }
+}
+
+macro_rules! delegate {
+    ($self:ident => $client:ident => $expr:expr) => {
+        match $self {
+            #[cfg(feature = "onnxruntime")]
+            Self::Local($client) => $expr,
+            Self::Remote($client) => $expr,
+        }
+    };
+}
+
+#[async_trait]
+impl Ipnis for IpnisAnyClient {
+    async fn protocol(&self) -> Result<String, IpnisError> {
+        delegate!(self => client => client.protocol().await)
+    }
+
+    async fn call_raw(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+    ) -> Result<Vec<Tensor>, IpnisError> {
+        delegate!(self => client => client.call_raw(model, inputs).await)
+    }
+
+    async fn call_raw_with_outputs(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+        outputs: &[String],
+    ) -> Result<Vec<Tensor>, IpnisError> {
+        delegate!(self => client => client.call_raw_with_outputs(model, inputs, outputs).await)
+    }
+
+    async fn call_batch(
+        &self,
+        model: &Model,
+        batches: Vec<Vec<Tensor>>,
+    ) -> Result<Vec<Vec<Tensor>>, IpnisError> {
+        delegate!(self => client => client.call_batch(model, batches).await)
+    }
+
+    async fn load_model(&self, path: &Path) -> Result<Model, IpnisError> {
+        delegate!(self => client => client.load_model(path).await)
+    }
+
+    async fn unload_model(&self, path: &Path) -> Result<bool, IpnisError> {
+        delegate!(self => client => client.unload_model(path).await)
+    }
+
+    async fn list_models(&self) -> Result<Vec<Path>, IpnisError> {
+        delegate!(self => client => client.list_models().await)
+    }
+}
@@ -41,6 +41,13 @@ impl<'a> Infer<'a> for IpnisServer {
     }
 }
 
+// The opcodes are defined in `ipnis_common::io`:
+//
+// * `Protocol`: returns the name of the inference backend.
+// * `Call`: performs the inference of the given model and inputs.
+// * `LoadModel`: loads and caches the model of the signed path.
+// * `UnloadModel`: drops the cached model of the signed path.
+// * `ListModels`: returns the paths of the cached models.
 handle_external_call!(
     server: IpnisServer => IpnisClientInner<IpiisServer>,
     name: run,
@@ -48,6 +55,8 @@ handle_external_call!(
         Protocol => handle_protocol,
         Call => handle_call,
         LoadModel => handle_load_model,
+        UnloadModel => handle_unload_model,
+        ListModels => handle_list_models,
     },
 );
 
@@ -124,4 +133,51 @@ impl IpnisServer {
             model: ::ipis::stream::DynStream::Owned(model),
         })
     }
+
+    async fn handle_unload_model(
+        client: &IpnisClientInner<IpiisServer>,
+        req: ::ipnis_common::io::request::UnloadModel<'static>,
+    ) -> Result<::ipnis_common::io::response::UnloadModel<'static>> {
+        // unpack sign
+        let sign_as_guarantee = req.__sign.into_owned().await?;
+
+        // unpack data
+        let path = sign_as_guarantee.data;
+
+        // handle data
+        let unloaded = client.unload_model(&path).await?;
+
+        // sign data
+        let server: &IpiisServer = client.as_ref();
+        let sign = server.sign_as_guarantor(sign_as_guarantee)?;
+
+        // pack data
+        Ok(::ipnis_common::io::response::UnloadModel {
+            __lifetime: Default::default(),
+            __sign: ::ipis::stream::DynStream::Owned(sign),
+            unloaded: ::ipis::stream::DynStream::Owned(unloaded),
+        })
+    }
+
+    async fn handle_list_models(
+        client: &IpnisClientInner<IpiisServer>,
+        req: ::ipnis_common::io::request::ListModels<'static>,
+    ) -> Result<::ipnis_common::io::response::ListModels<'static>> {
+        // unpack sign
+        let sign_as_guarantee = req.__sign.into_owned().await?;
+
+        // handle data
+        let paths = client.list_models().await?;
+
+        // sign data
+        let server: &IpiisServer = client.as_ref();
+        let sign = server.sign_as_guarantor(sign_as_guarantee)?;
+
+        // pack data
+        Ok(::ipnis_common::io::response::ListModels {
+            __lifetime: Default::default(),
+            __sign: ::ipis::stream::DynStream::Owned(sign),
+            paths: ::ipis::stream::DynStream::Owned(paths),
+        })
+    }
 }
@@ -0,0 +1,87 @@
+use ipis::{
+    core::{anyhow, value::hash::Hash},
+    path::Path,
+};
+
+use crate::tensor::ty::TensorType;
+
+/// The errors which can be handled by the callers, such as retrying or mapping into a status.
+///
+/// The methods of [`Ipnis`](crate::Ipnis) return them, and they are converted from `anyhow`,
+/// recovering the ones which have been wrapped into `anyhow::Error`.
+#[derive(Debug)]
+pub enum IpnisError {
+    /// The model is not stored in the storage.
+    ModelNotFound(Path),
+    /// The model could not be fetched from the storage.
+    ModelFetch(anyhow::Error),
+    LengthMismatch {
+        expected: u64,
+        given: u64,
+    },
+    HashMismatch {
+        expected: Hash,
+        given: Hash,
+    },
+    /// The model could not be compiled into a session.
+    SessionBuild(anyhow::Error),
+    /// The session has been failed while running the inference.
+    Inference(anyhow::Error),
+    OutputDtypeMismatch {
+        expected: TensorType,
+        given: TensorType,
+    },
+    /// The caller has stopped waiting on the inference.
+    Cancelled,
+    /// The model has been rejected before building a session,
+    /// such as being too large or malformed, so it would be rejected again.
+    InvalidModel(anyhow::Error),
+    /// The other errors, such as the invalid inputs.
+    Other(anyhow::Error),
+}
+
+impl From<anyhow::Error> for IpnisError {
+    fn from(error: anyhow::Error) -> Self {
+        error.downcast().unwrap_or_else(Self::Other)
+    }
+}
+
+impl ::std::fmt::Display for IpnisError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        match self {
+            Self::ModelNotFound(path) => write!(f, "No such model: {}", path.value),
+            Self::ModelFetch(error) => write!(f, "failed to fetch the model: {error}"),
+            Self::LengthMismatch { expected, given } => write!(
+                f,
+                "failed to validate the length: expected {expected}, but given {given}"
+            ),
+            Self::HashMismatch { expected, given } => write!(
+                f,
+                "failed to validate the hash: expected {expected}, but given {given}"
+            ),
+            Self::SessionBuild(error) => write!(f, "failed to build the session: {error}"),
+            Self::Inference(error) => write!(f, "failed to run the inference: {error}"),
+            Self::OutputDtypeMismatch { expected, given } => write!(
+                f,
+                "tensor type mismatched: expected {expected:?}, but given {given:?}"
+            ),
+            Self::Cancelled => write!(f, "the inference has been cancelled"),
+            Self::InvalidModel(error) => write!(f, "invalid model: {error}"),
+            Self::Other(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl ::std::error::Error for IpnisError {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match self {
+            Self::ModelFetch(error)
+            | Self::SessionBuild(error)
+            | Self::Inference(error)
+            | Self::InvalidModel(error) => Some(error.as_ref()),
+            // NOTE: the other errors are transparent
+            Self::Other(error) => error.chain().nth(1),
+            _ => None,
+        }
+    }
+}
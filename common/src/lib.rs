@@ -1,3 +1,4 @@
+pub extern crate half;
 #[cfg(feature = "image")]
 pub extern crate image;
 #[cfg(feature = "onnxruntime")]
@@ -5,6 +6,7 @@ pub extern crate onnxruntime;
 #[cfg(feature = "rust_tokenizers")]
 pub extern crate rust_tokenizers;
 
+pub mod error;
 pub mod model;
 pub mod nlp;
 pub mod tensor;
@@ -17,23 +19,42 @@ use ipis::{
     async_trait::async_trait,
     core::{
         account::{GuaranteeSigned, GuarantorSigned},
-        anyhow::{bail, Result},
+        anyhow::{self, anyhow, bail, Result},
         data::Data,
+        ndarray,
     },
+    futures::{stream, StreamExt, TryStreamExt},
     path::Path,
 };
 
 use self::{
-    model::Model,
-    tensor::{Tensor, ToTensor},
+    error::IpnisError,
+    model::{Model, ModelSignature, OutputMapping},
+    tensor::{element::TensorElement, Tensor, TensorData, ToTensor},
 };
 
+/// The maximum number of the requests performed concurrently in a batch.
+pub const MAX_CONCURRENT_BATCHES: usize = 8;
+
+/// The clients of the different backends, held uniformly.
+///
+/// The generic methods, such as `call`, are not available on the boxed clients.
+pub type BoxedIpnis = Box<dyn Ipnis + Send + Sync>;
+
+/// The neural interpretation service.
+///
+/// It is object-safe, so the different clients can be held as [`BoxedIpnis`].
 #[async_trait]
 pub trait Ipnis {
-    async fn protocol(&self) -> Result<String>;
+    async fn protocol(&self) -> Result<String, IpnisError>;
 
-    async fn call<T>(&self, model: &Model, inputs: &HashMap<String, T>) -> Result<Vec<Tensor>>
+    async fn call<T>(
+        &self,
+        model: &Model,
+        inputs: &HashMap<String, T>,
+    ) -> Result<Vec<Tensor>, IpnisError>
     where
+        Self: Sized,
         T: Send + Sync + ToTensor,
     {
         // collect inputs
@@ -52,9 +73,193 @@ pub trait Ipnis {
         self.call_raw(model, inputs).await
     }
 
-    async fn call_raw(&self, model: &Model, inputs: Vec<Tensor>) -> Result<Vec<Tensor>>;
+    async fn call_raw(&self, model: &Model, inputs: Vec<Tensor>)
+        -> Result<Vec<Tensor>, IpnisError>;
+
+    /// Performs the inference, returning the outputs by their names.
+    async fn call_map(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+    ) -> Result<HashMap<String, Tensor>, IpnisError> {
+        let outputs = self.call_raw(model, inputs).await?;
+
+        let mut map = HashMap::with_capacity(outputs.len());
+        for output in outputs {
+            if let Some(output) = map.insert(output.name.clone(), output) {
+                let name = &output.name;
+                return Err(anyhow!("duplicated output: {name}").into());
+            }
+        }
+        Ok(map)
+    }
+
+    /// Performs the inference with the arrays bound to the inputs of the same names.
+    async fn call_ndarray<T>(
+        &self,
+        model: &Model,
+        mut inputs: HashMap<String, ndarray::ArrayD<T>>,
+    ) -> Result<Vec<Tensor>, IpnisError>
+    where
+        Self: Sized,
+        T: TensorElement + Send,
+    {
+        // collect inputs
+        let inputs: Vec<_> = model
+            .inputs
+            .iter()
+            .map(|shape| match inputs.remove(&shape.name) {
+                Some(input) => Ok(Tensor::from((shape.name.clone(), input))),
+                None => {
+                    let name = &shape.name;
+                    bail!("No such input: {name}")
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        self.call_raw(model, inputs).await
+    }
+
+    /// Performs the inference with the borrowed inputs, so that they can be reused.
+    ///
+    /// The arrays of the tensors are reference-counted,
+    /// so the inputs are not copied but only shared with the inference.
+    async fn call_raw_ref(
+        &self,
+        model: &Model,
+        inputs: &[Tensor],
+    ) -> Result<Vec<Tensor>, IpnisError> {
+        self.call_raw(model, inputs.to_vec()).await
+    }
+
+    /// Performs the inference, returning only the given outputs in order.
+    async fn call_raw_with_outputs(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+        outputs: &[String],
+    ) -> Result<Vec<Tensor>, IpnisError> {
+        // validate outputs
+        if let Some(name) = outputs.iter().find(|name| model.output(name).is_none()) {
+            return Err(anyhow!("No such output: {name}").into());
+        }
+
+        let mut tensors = self.call_raw(model, inputs).await?;
+        Ok(outputs
+            .iter()
+            .map(|name| Tensor::find(&mut tensors, name))
+            .collect::<Result<_>>()?)
+    }
+
+    /// Performs the inference, returning the outputs as the arrays of the given element type.
+    async fn call_typed<T>(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+    ) -> Result<HashMap<String, ndarray::ArrayD<T>>, IpnisError>
+    where
+        Self: Sized,
+        T: TensorElement + Send,
+    {
+        Ok(self
+            .call_raw(model, inputs)
+            .await?
+            .into_iter()
+            .map(|output| match output.data {
+                TensorData::Dynamic(data) => Ok((output.name, T::try_from_dynamic(data)?)),
+                _ => {
+                    let shape = output.shape();
+                    bail!("unsupported shape yet: {shape:?}")
+                }
+            })
+            .collect::<Result<_>>()?)
+    }
+
+    /// Performs the inferences of the chained models in order, returning the last outputs.
+    ///
+    /// The outputs of each stage are renamed by the mapping of the next stage
+    /// and fed into it, without leaving the process.
+    /// The first mapping renames the given inputs.
+    async fn call_pipeline(
+        &self,
+        stages: &[(Model, OutputMapping)],
+        inputs: Vec<Tensor>,
+    ) -> Result<Vec<Tensor>, IpnisError> {
+        let mut tensors = inputs;
+        for (model, mapping) in stages {
+            // wire the previous outputs into the inputs
+            let mut wired: Vec<_> = tensors
+                .into_iter()
+                .map(|mut tensor| {
+                    if let Some(name) = mapping.get(&tensor.name) {
+                        tensor.name = name.clone();
+                    }
+                    tensor
+                })
+                .collect();
+
+            let inputs = model
+                .inputs
+                .iter()
+                .map(|shape| {
+                    let name = &shape.name;
+                    match wired.iter().position(|tensor| &tensor.name == name) {
+                        Some(index) => Ok(wired.swap_remove(index)),
+                        None => bail!("unresolved input: {name}"),
+                    }
+                })
+                .collect::<Result<_>>()?;
+
+            tensors = self.call_raw(model, inputs).await?;
+        }
+        Ok(tensors)
+    }
+
+    /// Performs the inferences of the multiple requests.
+    ///
+    /// The requests are stacked along the leading (batch) axis into a single inference
+    /// if they have the same input names, and all inputs are dynamic tensors
+    /// having the same types and the same shapes except the leading axis.
+    /// The stacked outputs should also have the leading (batch) axis.
+    ///
+    /// Otherwise, the requests are performed concurrently.
+    async fn call_batch(
+        &self,
+        model: &Model,
+        batches: Vec<Vec<Tensor>>,
+    ) -> Result<Vec<Vec<Tensor>>, IpnisError> {
+        match self::tensor::batch::stack(&batches) {
+            Some((inputs, sizes)) => {
+                let outputs = self.call_raw(model, inputs).await?;
+                Ok(self::tensor::batch::unstack(outputs, &sizes)?)
+            }
+            None => {
+                stream::iter(batches)
+                    .map(|inputs| self.call_raw(model, inputs))
+                    .buffered(MAX_CONCURRENT_BATCHES)
+                    .try_collect()
+                    .await
+            }
+        }
+    }
+
+    async fn load_model(&self, path: &Path) -> Result<Model, IpnisError>;
+
+    /// Loads the model, rejecting it if it does not match the expected signature.
+    async fn load_model_checked(
+        &self,
+        path: &Path,
+        expected: &ModelSignature,
+    ) -> Result<Model, IpnisError> {
+        let model = self.load_model(path).await?;
+        expected.validate(&model)?;
+        Ok(model)
+    }
+
+    async fn unload_model(&self, path: &Path) -> Result<bool, IpnisError>;
 
-    async fn load_model(&self, path: &Path) -> Result<Model>;
+    /// Returns the paths of the models which are currently loaded.
+    async fn list_models(&self) -> Result<Vec<Path>, IpnisError>;
 }
 
 #[async_trait]
@@ -62,61 +267,116 @@ impl<IpiisClient> Ipnis for IpiisClient
 where
     IpiisClient: Ipiis + Send + Sync,
 {
-    async fn protocol(&self) -> Result<String> {
-        // next target
-        let target = self.get_account_primary(KIND.as_ref()).await?;
-
-        // external call
-        let (protocol,) = external_call!(
-            client: self,
-            target: KIND.as_ref() => &target,
-            request: crate::io => Protocol,
-            sign: self.sign_owned(target, ())?,
-            inputs: { },
-            outputs: { protocol, },
-        );
-
-        // unpack response
-        Ok(protocol)
+    async fn protocol(&self) -> Result<String, IpnisError> {
+        let response = async {
+            // next target
+            let target = self.get_account_primary(KIND.as_ref()).await?;
+
+            // external call
+            let (protocol,) = external_call!(
+                client: self,
+                target: KIND.as_ref() => &target,
+                request: crate::io => Protocol,
+                sign: self.sign_owned(target, ())?,
+                inputs: { },
+                outputs: { protocol, },
+            );
+
+            // unpack response
+            Ok::<_, anyhow::Error>(protocol)
+        };
+        Ok(response.await?)
     }
 
-    async fn call_raw(&self, model: &Model, inputs: Vec<Tensor>) -> Result<Vec<Tensor>> {
-        // next target
-        let target = self.get_account_primary(KIND.as_ref()).await?;
-
-        // external call
-        let (outputs,) = external_call!(
-            client: self,
-            target: KIND.as_ref() => &target,
-            request: crate::io => Call,
-            sign: self.sign_owned(target, model.path)?,
-            inputs: {
-                model: model.clone(),
-                inputs: inputs,
-            },
-            outputs: { outputs, },
-        );
-
-        // unpack response
-        Ok(outputs)
+    async fn call_raw(
+        &self,
+        model: &Model,
+        inputs: Vec<Tensor>,
+    ) -> Result<Vec<Tensor>, IpnisError> {
+        let response = async {
+            // next target
+            let target = self.get_account_primary(KIND.as_ref()).await?;
+
+            // external call
+            let (outputs,) = external_call!(
+                client: self,
+                target: KIND.as_ref() => &target,
+                request: crate::io => Call,
+                sign: self.sign_owned(target, model.path)?,
+                inputs: {
+                    model: model.clone(),
+                    inputs: inputs,
+                },
+                outputs: { outputs, },
+            );
+
+            // unpack response
+            Ok::<_, anyhow::Error>(outputs)
+        };
+        Ok(response.await?)
     }
 
-    async fn load_model(&self, path: &Path) -> Result<Model> {
-        // next target
-        let target = self.get_account_primary(KIND.as_ref()).await?;
-
-        // external call
-        let (model,) = external_call!(
-            client: self,
-            target: KIND.as_ref() => &target,
-            request: crate::io => LoadModel,
-            sign: self.sign_owned(target, *path)?,
-            inputs: { },
-            outputs: { model, },
-        );
-
-        // unpack response
-        Ok(model)
+    async fn load_model(&self, path: &Path) -> Result<Model, IpnisError> {
+        let response = async {
+            // next target
+            let target = self.get_account_primary(KIND.as_ref()).await?;
+
+            // external call
+            let (model,) = external_call!(
+                client: self,
+                target: KIND.as_ref() => &target,
+                request: crate::io => LoadModel,
+                sign: self.sign_owned(target, *path)?,
+                inputs: { },
+                outputs: { model, },
+            );
+
+            // unpack response
+            Ok::<_, anyhow::Error>(model)
+        };
+        Ok(response.await?)
+    }
+
+    async fn unload_model(&self, path: &Path) -> Result<bool, IpnisError> {
+        let response = async {
+            // next target
+            let target = self.get_account_primary(KIND.as_ref()).await?;
+
+            // external call
+            let (unloaded,) = external_call!(
+                client: self,
+                target: KIND.as_ref() => &target,
+                request: crate::io => UnloadModel,
+                sign: self.sign_owned(target, *path)?,
+                inputs: { },
+                outputs: { unloaded, },
+            );
+
+            // unpack response
+            Ok::<_, anyhow::Error>(unloaded)
+        };
+        Ok(response.await?)
+    }
+
+    async fn list_models(&self) -> Result<Vec<Path>, IpnisError> {
+        let response = async {
+            // next target
+            let target = self.get_account_primary(KIND.as_ref()).await?;
+
+            // external call
+            let (paths,) = external_call!(
+                client: self,
+                target: KIND.as_ref() => &target,
+                request: crate::io => ListModels,
+                sign: self.sign_owned(target, ())?,
+                inputs: { },
+                outputs: { paths, },
+            );
+
+            // unpack response
+            Ok::<_, anyhow::Error>(paths)
+        };
+        Ok(response.await?)
     }
 }
 
@@ -151,6 +411,24 @@ define_io! {
         output_sign: Data<GuarantorSigned, Path>,
         generics: { },
     },
+    UnloadModel {
+        inputs: { },
+        input_sign: Data<GuaranteeSigned, Path>,
+        outputs: {
+            unloaded: bool,
+        },
+        output_sign: Data<GuarantorSigned, Path>,
+        generics: { },
+    },
+    ListModels {
+        inputs: { },
+        input_sign: Data<GuaranteeSigned, ()>,
+        outputs: {
+            paths: Vec<Path>,
+        },
+        output_sign: Data<GuarantorSigned, ()>,
+        generics: { },
+    },
 }
 
 ::ipis::lazy_static::lazy_static! {
@@ -1,8 +1,23 @@
+use std::collections::HashMap;
+
 use bytecheck::CheckBytes;
-use ipis::{core::signed::IsSigned, path::Path};
+use ipis::{
+    core::{
+        anyhow::{bail, Result},
+        signed::IsSigned,
+    },
+    path::Path,
+};
+#[cfg(feature = "onnxruntime")]
+use onnxruntime::session::Session;
 use rkyv::{Archive, Deserialize, Serialize};
 
-use crate::tensor::shape::Shape;
+use crate::tensor::{dynamic::DynamicTensorData, shape::Shape, Tensor};
+
+/// Renames the outputs of the previous stage into the inputs of the next stage in a pipeline.
+///
+/// The tensors which are not mapped keep their names.
+pub type OutputMapping = HashMap<String, String>;
 
 #[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
 #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
@@ -13,3 +28,183 @@ pub struct Model {
 }
 
 impl IsSigned for Model {}
+
+impl Model {
+    pub fn input(&self, name: &str) -> Option<&Shape> {
+        self.inputs.iter().find(|shape| shape.name == name)
+    }
+
+    pub fn output(&self, name: &str) -> Option<&Shape> {
+        self.outputs.iter().find(|shape| shape.name == name)
+    }
+
+    /// Synthesizes the zero-filled inputs, such as for warming up the sessions.
+    ///
+    /// The dynamic axes are filled with the given size.
+    pub fn example_inputs(&self, dynamic_size: usize) -> Vec<Tensor> {
+        self.inputs
+            .iter()
+            .map(|shape| {
+                let dimensions: Vec<_> = shape
+                    .to_vec()
+                    .into_iter()
+                    .map(|dimension| dimension.unwrap_or(dynamic_size))
+                    .collect();
+                Tensor {
+                    name: shape.name.clone(),
+                    data: DynamicTensorData::zeros(shape.ty(), &dimensions).into(),
+                }
+            })
+            .collect()
+    }
+
+    /// Checks the number, the types and the shapes of the given inputs.
+    pub fn validate_inputs(&self, inputs: &[Tensor]) -> Result<()> {
+        if inputs.len() != self.inputs.len() {
+            let expected = self.inputs.len();
+            let given = inputs.len();
+            bail!("unexpected inputs: Expected {expected}, Given {given}");
+        }
+
+        self.inputs.iter().try_for_each(|shape| {
+            match inputs.iter().find(|input| input.name == shape.name) {
+                Some(input) => shape.validate(&input.shape()),
+                None => {
+                    let name = &shape.name;
+                    bail!("No such input: {name}")
+                }
+            }
+        })
+    }
+
+    /// Returns the output shapes, resolving their symbolic axes with the given inputs.
+    ///
+    /// The axes which cannot be resolved, such as the data-dependent ones, remain dynamic.
+    pub fn resolve_outputs(&self, inputs: &[Tensor]) -> Result<Vec<Shape>> {
+        // collect the concrete sizes of the symbolic axes
+        let mut sizes = HashMap::default();
+        for shape in &self.inputs {
+            if let Some(input) = inputs.iter().find(|input| input.name == shape.name) {
+                let dimensions = input.shape().to_vec();
+                for (name, size) in shape.symbolic_dimensions().iter().zip(dimensions) {
+                    if let (Some(name), Some(size)) = (name, size) {
+                        sizes.insert(name.as_str(), size);
+                    }
+                }
+            }
+        }
+
+        self.outputs
+            .iter()
+            .map(|shape| shape.resolve(&sizes))
+            .collect()
+    }
+}
+
+/// The expected contract of the model, to guard against the silent model swaps.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModelSignature {
+    pub inputs: Vec<Shape>,
+    pub outputs: Vec<Shape>,
+}
+
+impl From<&Model> for ModelSignature {
+    fn from(value: &Model) -> Self {
+        Self {
+            inputs: value.inputs.clone(),
+            outputs: value.outputs.clone(),
+        }
+    }
+}
+
+impl ModelSignature {
+    /// Checks whether the model matches the signature, reporting all differences.
+    ///
+    /// The dynamic axes of either side are matched with anything.
+    pub fn validate(&self, model: &Model) -> Result<()> {
+        fn diff(kind: &str, expected: &[Shape], given: &[Shape], errors: &mut Vec<String>) {
+            for shape in expected {
+                let name = &shape.name;
+                match given.iter().find(|e| &e.name == name) {
+                    Some(given) => {
+                        if shape.ty() != given.ty() {
+                            let expected = shape.ty();
+                            let given = given.ty();
+                            errors.push(format!(
+                                "{kind} {name}: expected type {expected:?}, but given {given:?}"
+                            ));
+                        }
+
+                        let expected = shape.to_vec();
+                        let given = given.to_vec();
+                        if expected.len() != given.len()
+                            || expected.iter().zip(&given).any(|pair| match pair {
+                                (Some(expected), Some(given)) => expected != given,
+                                _ => false,
+                            })
+                        {
+                            errors.push(format!(
+                                "{kind} {name}: expected shape {expected:?}, but given {given:?}"
+                            ));
+                        }
+                    }
+                    None => errors.push(format!("{kind} {name}: missing")),
+                }
+            }
+            for shape in given {
+                let name = &shape.name;
+                if !expected.iter().any(|e| &e.name == name) {
+                    errors.push(format!("{kind} {name}: unexpected"));
+                }
+            }
+        }
+
+        let mut errors = Vec::new();
+        diff("input", &self.inputs, &model.inputs, &mut errors);
+        diff("output", &self.outputs, &model.outputs, &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            let errors = errors.join("; ");
+            bail!("model signature mismatched: {errors}")
+        }
+    }
+}
+
+/// The metadata section of the ONNX model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelMetadata {
+    pub producer_name: String,
+    pub graph_name: String,
+    pub model_version: i64,
+    pub domain: String,
+    pub description: String,
+    pub custom: HashMap<String, String>,
+}
+
+#[cfg(feature = "onnxruntime")]
+impl TryFrom<&'_ Session> for ModelMetadata {
+    type Error = ::ipis::core::anyhow::Error;
+
+    fn try_from(value: &Session) -> Result<Self, Self::Error> {
+        let metadata = value.metadata()?;
+
+        Ok(Self {
+            producer_name: metadata.producer_name()?,
+            graph_name: metadata.graph_name()?,
+            model_version: metadata.version()?,
+            domain: metadata.domain()?,
+            description: metadata.description()?,
+            custom: metadata
+                .custom_keys()?
+                .into_iter()
+                .filter_map(|key| match metadata.custom(&key) {
+                    Ok(Some(value)) => Some(Ok((key, value))),
+                    Ok(None) => None,
+                    Err(error) => Some(Err(error)),
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
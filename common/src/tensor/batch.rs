@@ -0,0 +1,221 @@
+use ipis::core::anyhow::{bail, Result};
+
+use super::{dynamic::DynamicTensorData, Tensor, TensorData};
+
+/// Stacks the requests along the leading (batch) axis into a single request.
+///
+/// Returns `None` if the requests cannot be stacked, or there is nothing to stack.
+/// The requests should have the same input names, and all inputs should be
+/// dynamic tensors having the same types and the same shapes except the leading axis.
+pub fn stack(batches: &[Vec<Tensor>]) -> Option<(Vec<Tensor>, Vec<usize>)> {
+    let first = batches.first()?;
+    if batches.len() < 2 || batches.iter().any(|inputs| inputs.len() != first.len()) {
+        return None;
+    }
+
+    let sizes = batches
+        .iter()
+        .map(|inputs| {
+            let sizes = inputs
+                .iter()
+                .map(|input| match &input.data {
+                    TensorData::Dynamic(data) => data.batch_size(),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+
+            // all inputs should have the same batch size
+            match sizes.first() {
+                Some(size) if sizes.iter().all(|e| e == size) => Some(*size),
+                _ => None,
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let inputs = first
+        .iter()
+        .map(|input| {
+            let tensors = batches
+                .iter()
+                .map(|inputs| {
+                    let tensor = inputs.iter().find(|e| e.name == input.name)?;
+                    match &tensor.data {
+                        TensorData::Dynamic(data) => Some(data),
+                        _ => None,
+                    }
+                })
+                .collect::<Option<Vec<_>>>()?;
+
+            // the shapes except the leading axis should be same
+            let shape = &tensors.first()?.shape()[1..];
+            if tensors.iter().any(|tensor| &tensor.shape()[1..] != shape) {
+                return None;
+            }
+
+            DynamicTensorData::concatenate(&tensors)
+                .ok()
+                .map(|data| Tensor {
+                    name: input.name.clone(),
+                    data: data.into(),
+                })
+        })
+        .collect::<Option<_>>()?;
+
+    Some((inputs, sizes))
+}
+
+/// Splits the stacked outputs back into the requests with the given batch sizes.
+pub fn unstack(outputs: Vec<Tensor>, sizes: &[usize]) -> Result<Vec<Vec<Tensor>>> {
+    let mut batches: Vec<Vec<_>> = sizes.iter().map(|_| Vec::new()).collect();
+
+    for output in outputs {
+        let data = match output.data {
+            TensorData::Dynamic(data) => data,
+            _ => {
+                let shape = output.shape();
+                bail!("unsupported shape yet: {shape:?}")
+            }
+        };
+
+        for (batch, data) in batches.iter_mut().zip(data.split(sizes)?) {
+            batch.push(Tensor {
+                name: output.name.clone(),
+                data: data.into(),
+            });
+        }
+    }
+    Ok(batches)
+}
+
+/// Splits the request along the leading (batch) axis into the sub-requests of `max_batch_size`.
+///
+/// Returns `None` if the request fits in, or cannot be split.
+/// All inputs should be dynamic tensors having the same batch size.
+pub fn split(inputs: &[Tensor], max_batch_size: usize) -> Option<Vec<Vec<Tensor>>> {
+    let max_batch_size = max_batch_size.max(1);
+
+    let tensors = inputs
+        .iter()
+        .map(|input| match &input.data {
+            TensorData::Dynamic(data) => Some(data),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    // all inputs should have the same batch size
+    let batch_size = tensors.first()?.batch_size()?;
+    if batch_size <= max_batch_size
+        || tensors
+            .iter()
+            .any(|tensor| tensor.batch_size() != Some(batch_size))
+    {
+        return None;
+    }
+
+    let sizes: Vec<_> = (0..batch_size)
+        .step_by(max_batch_size)
+        .map(|offset| max_batch_size.min(batch_size - offset))
+        .collect();
+
+    let mut batches: Vec<Vec<_>> = sizes
+        .iter()
+        .map(|_| Vec::with_capacity(inputs.len()))
+        .collect();
+    for (input, data) in inputs.iter().zip(tensors) {
+        for (batch, data) in batches.iter_mut().zip(data.split(&sizes).ok()?) {
+            batch.push(Tensor {
+                name: input.name.clone(),
+                data: data.into(),
+            });
+        }
+    }
+    Some(batches)
+}
+
+/// Concatenates the outputs of the sub-requests along the leading (batch) axis, in order.
+pub fn concatenate(mut batches: Vec<Vec<Tensor>>) -> Result<Vec<Tensor>> {
+    if batches.len() == 1 {
+        return Ok(batches.remove(0));
+    }
+
+    match stack(&batches) {
+        Some((outputs, _)) => Ok(outputs),
+        None => bail!("the outputs cannot be concatenated along the batch axis"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ipis::core::{ndarray, value::array::Array};
+
+    use super::*;
+
+    fn request(batch_size: usize, offset: f32) -> Vec<Tensor> {
+        let pixels = ndarray::ArrayD::from_elem(vec![batch_size, 2], offset);
+        let ids = ndarray::ArrayD::from_elem(vec![batch_size], 7i64);
+
+        vec![
+            Tensor {
+                name: "pixels".into(),
+                data: DynamicTensorData::F32(Array(pixels.into_shared())).into(),
+            },
+            Tensor {
+                name: "ids".into(),
+                data: DynamicTensorData::I64(Array(ids.into_shared())).into(),
+            },
+        ]
+    }
+
+    fn shape(tensor: &Tensor) -> &[usize] {
+        match &tensor.data {
+            TensorData::Dynamic(data) => data.shape(),
+            _ => unreachable!("expected a dynamic tensor"),
+        }
+    }
+
+    #[test]
+    fn test_stack() {
+        let mut second = request(2, 1.0);
+        second.reverse();
+        let batches = vec![request(1, 0.0), second];
+
+        let (inputs, sizes) = stack(&batches).unwrap();
+        assert_eq!(sizes, [1, 2]);
+        assert_eq!(inputs[0].name, "pixels");
+        assert_eq!(shape(&inputs[0]), [3, 2]);
+        assert_eq!(shape(&inputs[1]), [3]);
+
+        // the stacked outputs are split back into the requests
+        let outputs = unstack(inputs, &sizes).unwrap();
+        assert_eq!(outputs, [request(1, 0.0), request(2, 1.0)]);
+    }
+
+    #[test]
+    fn test_stack_unstackable() {
+        // nothing to stack
+        assert!(stack(&[request(1, 0.0)]).is_none());
+
+        // mismatched shapes except the leading axis
+        let mut other = request(1, 0.0);
+        other[0].data = DynamicTensorData::F32(Array(ndarray::ArcArray::zeros(vec![1, 3]))).into();
+        assert!(stack(&[request(1, 0.0), other]).is_none());
+
+        // mismatched inputs
+        let mut other = request(1, 0.0);
+        other.pop();
+        assert!(stack(&[request(1, 0.0), other]).is_none());
+    }
+
+    #[test]
+    fn test_split() {
+        let inputs = request(5, 1.0);
+        assert!(split(&inputs, 5).is_none());
+
+        let batches = split(&inputs, 2).unwrap();
+        let sizes: Vec<_> = batches.iter().map(|batch| shape(&batch[0])[0]).collect();
+        assert_eq!(sizes, [2, 2, 1]);
+
+        // the outputs of the sub-requests are concatenated back in order
+        assert_eq!(concatenate(batches).unwrap(), inputs);
+    }
+}
@@ -1,19 +1,39 @@
 use bytecheck::CheckBytes;
-use ipis::core::{ndarray, signed::IsSigned, value::array::Array};
+use ipis::core::{
+    anyhow::{anyhow, bail, Result},
+    ndarray,
+    signed::IsSigned,
+    value::array::Array,
+};
 #[cfg(feature = "onnxruntime")]
 use onnxruntime::{
     session::Session,
-    tensor::{AsOrtTensorDyn, OrtTensorDyn},
+    tensor::{AsOrtTensorDyn, DynOrtTensor, OrtTensorDyn},
 };
 use rkyv::{Archive, Deserialize, Serialize};
 
 use super::{dimension::Dimensions, ty::TensorType, AsTensorData, TensorData};
 
+/// NOTE: the variants are archived by their order on the wire,
+///       so the new ones should be appended after the others
 #[derive(Clone, Debug, PartialEq, Archive, Serialize, Deserialize)]
 #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
 pub enum DynamicTensorData {
     U8(Array<u8, ndarray::IxDyn>),
     F32(Array<f32, ndarray::IxDyn>),
+    I32(Array<i32, ndarray::IxDyn>),
+    /// Fed into the ONNX Runtime as they are, such as the token ids of the transformers.
+    I64(Array<i64, ndarray::IxDyn>),
+    /// Half-precision floats, stored as their raw bits.
+    F16(Array<u16, ndarray::IxDyn>),
+    /// Variable-length UTF-8 strings.
+    String(Array<String, ndarray::IxDyn>),
+    /// Signed bytes, such as the quantized tensors.
+    I8(Array<i8, ndarray::IxDyn>),
+    /// Booleans, such as the attention masks.
+    ///
+    /// Each element takes a single byte, as the ONNX Runtime expects.
+    Bool(Array<bool, ndarray::IxDyn>),
 }
 
 impl IsSigned for DynamicTensorData {}
@@ -31,17 +51,354 @@ impl<'t> AsOrtTensorDyn<'t> for DynamicTensorData {
         'm: 't,
     {
         match self {
+            Self::I32(v) => v.as_ort_tensor_dyn(session),
+            Self::I64(v) => v.as_ort_tensor_dyn(session),
+            Self::I8(v) => v.as_ort_tensor_dyn(session),
             Self::U8(v) => v.as_ort_tensor_dyn(session),
+            Self::F16(v) => v.mapv(::half::f16::from_bits).as_ort_tensor_dyn(session),
             Self::F32(v) => v.as_ort_tensor_dyn(session),
+            Self::Bool(v) => v.as_ort_tensor_dyn(session),
+            Self::String(v) => v.as_ort_tensor_dyn(session),
+        }
+    }
+}
+
+#[cfg(feature = "onnxruntime")]
+impl DynamicTensorData {
+    /// Extracts the output tensor with the given element type.
+    pub fn from_ort_tensor(
+        ty: TensorType,
+        tensor: DynOrtTensor<'_, ndarray::IxDyn>,
+    ) -> Result<Self> {
+        fn extract<T>(
+            tensor: &DynOrtTensor<'_, ndarray::IxDyn>,
+        ) -> Result<ndarray::ArcArray<T, ndarray::IxDyn>>
+        where
+            T: Clone + ::onnxruntime::TypeToTensorElementDataType,
+        {
+            Ok(tensor.try_extract::<T>()?.view().to_owned().into_shared())
+        }
+
+        match ty {
+            TensorType::I32 => extract(&tensor).map(Array).map(Self::I32),
+            TensorType::I64 => extract(&tensor).map(Array).map(Self::I64),
+            TensorType::I8 => extract(&tensor).map(Array).map(Self::I8),
+            TensorType::U8 => extract(&tensor).map(Array).map(Self::U8),
+            TensorType::F16 => extract::<::half::f16>(&tensor)
+                .map(|v| Array(v.mapv(::half::f16::to_bits).into_shared()))
+                .map(Self::F16),
+            TensorType::F32 => extract(&tensor).map(Array).map(Self::F32),
+            TensorType::Bool => extract(&tensor).map(Array).map(Self::Bool),
+            TensorType::String => extract(&tensor).map(Array).map(Self::String),
+        }
+    }
+}
+
+#[cfg(feature = "onnxruntime")]
+impl DynamicTensorData {
+    /// Copies the output tensor of the same type into this, without allocating a new array.
+    ///
+    /// Returns `false` and leaves this unchanged if the shapes are mismatched.
+    pub fn assign_ort_tensor(&mut self, tensor: &DynOrtTensor<'_, ndarray::IxDyn>) -> Result<bool> {
+        fn assign<T, U>(
+            array: &mut Array<U, ndarray::IxDyn>,
+            tensor: &DynOrtTensor<'_, ndarray::IxDyn>,
+            f: impl Fn(&T) -> U,
+        ) -> Result<bool>
+        where
+            T: Clone + ::onnxruntime::TypeToTensorElementDataType,
+            U: Clone,
+        {
+            let extracted = tensor.try_extract::<T>()?;
+            let view = extracted.view();
+            if view.shape() != array.shape() {
+                return Ok(false);
+            }
+
+            // NOTE: the shared array is copied here, so that the others are not affected
+            array.0.zip_mut_with(&view, |a, b| *a = f(b));
+            Ok(true)
+        }
+
+        match self {
+            Self::I32(v) => assign(v, tensor, i32::clone),
+            Self::I64(v) => assign(v, tensor, i64::clone),
+            Self::I8(v) => assign(v, tensor, i8::clone),
+            Self::U8(v) => assign(v, tensor, u8::clone),
+            Self::F16(v) => assign(v, tensor, |e: &::half::f16| e.to_bits()),
+            Self::F32(v) => assign(v, tensor, f32::clone),
+            Self::Bool(v) => assign(v, tensor, bool::clone),
+            Self::String(v) => assign(v, tensor, String::clone),
+        }
+    }
+}
+
+impl DynamicTensorData {
+    /// Creates the tensor of the given type and shape, filled with zeros or the empty strings.
+    pub fn zeros(ty: TensorType, shape: &[usize]) -> Self {
+        fn zeros<T>(shape: &[usize]) -> Array<T, ndarray::IxDyn>
+        where
+            T: Clone + Default,
+        {
+            Array(ndarray::ArcArray::default(shape))
+        }
+
+        match ty {
+            TensorType::I32 => Self::I32(zeros(shape)),
+            TensorType::I64 => Self::I64(zeros(shape)),
+            TensorType::I8 => Self::I8(zeros(shape)),
+            TensorType::U8 => Self::U8(zeros(shape)),
+            TensorType::F16 => Self::F16(zeros(shape)),
+            TensorType::F32 => Self::F32(zeros(shape)),
+            TensorType::Bool => Self::Bool(zeros(shape)),
+            TensorType::String => Self::String(zeros(shape)),
+        }
+    }
+
+    /// Returns the size of the leading (batch) axis.
+    pub fn batch_size(&self) -> Option<usize> {
+        self.shape().first().copied()
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        match self {
+            Self::I32(v) => v.shape(),
+            Self::I64(v) => v.shape(),
+            Self::I8(v) => v.shape(),
+            Self::U8(v) => v.shape(),
+            Self::F16(v) => v.shape(),
+            Self::F32(v) => v.shape(),
+            Self::Bool(v) => v.shape(),
+            Self::String(v) => v.shape(),
+        }
+    }
+
+    /// Checks whether the elements are laid out contiguously in the row-major (C) order,
+    /// as the ONNX Runtime reads them from the raw memory.
+    ///
+    /// The column-major (Fortran) or the transposed arrays are rejected,
+    /// so they should be copied into the standard layout first.
+    /// The buffers holding more elements than the shapes, such as of the sliced arrays,
+    /// are rejected as well.
+    /// The scalars (rank-0) have a single element, and the zero-sized tensors have none.
+    ///
+    /// Note that the buffers shared with the others are copied while taking them.
+    pub fn validate(self) -> Result<Self> {
+        fn validate<T>(array: Array<T, ndarray::IxDyn>) -> Result<Array<T, ndarray::IxDyn>>
+        where
+            T: Clone,
+        {
+            if !array.is_standard_layout() {
+                bail!("the elements are not laid out contiguously in the row-major order")
+            }
+
+            let shape = array.shape().to_vec();
+            let expected: usize = shape.iter().product();
+            let elements = array.0.into_owned().into_raw_vec();
+            let given = elements.len();
+            if given != expected {
+                bail!("number of the elements mismatched: expected {expected}, but given {given}")
+            }
+            Ok(Array(
+                ndarray::ArrayD::from_shape_vec(shape, elements)?.into_shared(),
+            ))
+        }
+
+        match self {
+            Self::I32(v) => validate(v).map(Self::I32),
+            Self::I64(v) => validate(v).map(Self::I64),
+            Self::I8(v) => validate(v).map(Self::I8),
+            Self::U8(v) => validate(v).map(Self::U8),
+            Self::F16(v) => validate(v).map(Self::F16),
+            Self::F32(v) => validate(v).map(Self::F32),
+            Self::Bool(v) => validate(v).map(Self::Bool),
+            Self::String(v) => validate(v).map(Self::String),
+        }
+    }
+
+    /// Concatenates the tensors along the leading (batch) axis.
+    pub fn concatenate(tensors: &[&Self]) -> Result<Self> {
+        fn collect<'a, T>(
+            tensors: &[&'a DynamicTensorData],
+            f: impl Fn(&'a DynamicTensorData) -> Option<&'a Array<T, ndarray::IxDyn>>,
+        ) -> Result<Array<T, ndarray::IxDyn>>
+        where
+            T: Clone,
+        {
+            let arrays = tensors
+                .iter()
+                .map(|tensor| f(tensor).ok_or_else(|| anyhow!("tensor types mismatched")))
+                .collect::<Result<Vec<_>>>()?;
+            let views: Vec<_> = arrays.iter().map(|array| array.view()).collect();
+            Ok(Array(
+                ndarray::concatenate(ndarray::Axis(0), &views)?.into_shared(),
+            ))
+        }
+
+        match tensors.first() {
+            Some(Self::I32(_)) => collect(tensors, |tensor| match tensor {
+                Self::I32(v) => Some(v),
+                _ => None,
+            })
+            .map(Self::I32),
+            Some(Self::I64(_)) => collect(tensors, |tensor| match tensor {
+                Self::I64(v) => Some(v),
+                _ => None,
+            })
+            .map(Self::I64),
+            Some(Self::I8(_)) => collect(tensors, |tensor| match tensor {
+                Self::I8(v) => Some(v),
+                _ => None,
+            })
+            .map(Self::I8),
+            Some(Self::U8(_)) => collect(tensors, |tensor| match tensor {
+                Self::U8(v) => Some(v),
+                _ => None,
+            })
+            .map(Self::U8),
+            Some(Self::F16(_)) => collect(tensors, |tensor| match tensor {
+                Self::F16(v) => Some(v),
+                _ => None,
+            })
+            .map(Self::F16),
+            Some(Self::F32(_)) => collect(tensors, |tensor| match tensor {
+                Self::F32(v) => Some(v),
+                _ => None,
+            })
+            .map(Self::F32),
+            Some(Self::Bool(_)) => collect(tensors, |tensor| match tensor {
+                Self::Bool(v) => Some(v),
+                _ => None,
+            })
+            .map(Self::Bool),
+            Some(Self::String(_)) => collect(tensors, |tensor| match tensor {
+                Self::String(v) => Some(v),
+                _ => None,
+            })
+            .map(Self::String),
+            None => bail!("no tensors to concatenate"),
+        }
+    }
+
+    /// Converts the elements into the given type, allocating a new array.
+    ///
+    /// The integer targets are saturated into their ranges, rounding towards zero,
+    /// and NaN becomes zero. The booleans become `true` unless the values are zero.
+    /// The strings cannot be converted from or into the numbers.
+    pub fn cast(&self, ty: TensorType) -> Result<Self> {
+        fn wrap<T>(array: ndarray::ArrayD<T>) -> Array<T, ndarray::IxDyn> {
+            Array(array.into_shared())
+        }
+
+        if self.ty() == ty {
+            return Ok(self.clone());
+        }
+
+        // NOTE: all numeric types are represented exactly in f64, except the huge i64 values
+        let values = match self {
+            Self::I32(v) => v.mapv(f64::from),
+            Self::I64(v) => v.mapv(|e| e as f64),
+            Self::I8(v) => v.mapv(f64::from),
+            Self::U8(v) => v.mapv(f64::from),
+            Self::F16(v) => v.mapv(|e| ::half::f16::from_bits(e).to_f64()),
+            Self::F32(v) => v.mapv(f64::from),
+            Self::Bool(v) => v.mapv(|e| f64::from(u8::from(e))),
+            Self::String(_) => {
+                bail!("failed to cast the tensor: expected numbers, but given String")
+            }
+        };
+
+        // NOTE: the float-to-integer `as` casts are saturating
+        Ok(match ty {
+            TensorType::I32 => Self::I32(wrap(values.mapv(|e| e as i32))),
+            TensorType::I64 => Self::I64(wrap(values.mapv(|e| e as i64))),
+            TensorType::I8 => Self::I8(wrap(values.mapv(|e| e as i8))),
+            TensorType::U8 => Self::U8(wrap(values.mapv(|e| e as u8))),
+            TensorType::F16 => Self::F16(wrap(values.mapv(|e| ::half::f16::from_f64(e).to_bits()))),
+            TensorType::F32 => Self::F32(wrap(values.mapv(|e| e as f32))),
+            TensorType::Bool => Self::Bool(wrap(values.mapv(|e| e != 0.0))),
+            TensorType::String => {
+                let given = self.ty();
+                bail!("failed to cast the tensor: expected String, but given {given:?}")
+            }
+        })
+    }
+
+    /// Converts the elements into f32, allocating a new array unless they are already.
+    pub fn to_f32(&self) -> Result<Array<f32, ndarray::IxDyn>> {
+        match self.cast(TensorType::F32)? {
+            Self::F32(v) => Ok(v),
+            data => {
+                let given = data.ty();
+                bail!("failed to cast the tensor: expected F32, but given {given:?}")
+            }
+        }
+    }
+
+    /// Converts the elements into f16 as their raw bits,
+    /// allocating a new array unless they are already.
+    pub fn to_f16(&self) -> Result<Array<u16, ndarray::IxDyn>> {
+        match self.cast(TensorType::F16)? {
+            Self::F16(v) => Ok(v),
+            data => {
+                let given = data.ty();
+                bail!("failed to cast the tensor: expected F16, but given {given:?}")
+            }
         }
     }
+
+    /// Splits the tensor along the leading (batch) axis with the given sizes.
+    pub fn split(&self, sizes: &[usize]) -> Result<Vec<Self>> {
+        fn split<T>(
+            array: &Array<T, ndarray::IxDyn>,
+            sizes: &[usize],
+        ) -> Vec<Array<T, ndarray::IxDyn>>
+        where
+            T: Clone,
+        {
+            let mut offset = 0;
+            sizes
+                .iter()
+                .map(|size| {
+                    let slice = ndarray::Slice::from(offset..offset + size);
+                    offset += size;
+                    Array(array.slice_axis(ndarray::Axis(0), slice).to_shared())
+                })
+                .collect()
+        }
+
+        let batch_size = self.batch_size().unwrap_or_default();
+        let total: usize = sizes.iter().sum();
+        if batch_size != total {
+            bail!(
+                "failed to split the tensor: expected batch size {total}, but given {batch_size}"
+            );
+        }
+
+        Ok(match self {
+            Self::I32(v) => split(v, sizes).into_iter().map(Self::I32).collect(),
+            Self::I64(v) => split(v, sizes).into_iter().map(Self::I64).collect(),
+            Self::I8(v) => split(v, sizes).into_iter().map(Self::I8).collect(),
+            Self::U8(v) => split(v, sizes).into_iter().map(Self::U8).collect(),
+            Self::F16(v) => split(v, sizes).into_iter().map(Self::F16).collect(),
+            Self::F32(v) => split(v, sizes).into_iter().map(Self::F32).collect(),
+            Self::Bool(v) => split(v, sizes).into_iter().map(Self::Bool).collect(),
+            Self::String(v) => split(v, sizes).into_iter().map(Self::String).collect(),
+        })
+    }
 }
 
 impl AsTensorData for DynamicTensorData {
     fn ty(&self) -> TensorType {
         match self {
+            Self::I32(_) => TensorType::I32,
+            Self::I64(_) => TensorType::I64,
+            Self::I8(_) => TensorType::I8,
             Self::U8(_) => TensorType::U8,
+            Self::F16(_) => TensorType::F16,
             Self::F32(_) => TensorType::F32,
+            Self::Bool(_) => TensorType::Bool,
+            Self::String(_) => TensorType::String,
         }
     }
 
@@ -51,8 +408,204 @@ impl AsTensorData for DynamicTensorData {
         }
 
         match self {
+            Self::I32(v) => dimensions_with_shape(v.shape()),
+            Self::I64(v) => dimensions_with_shape(v.shape()),
+            Self::I8(v) => dimensions_with_shape(v.shape()),
             Self::U8(v) => dimensions_with_shape(v.shape()),
+            Self::F16(v) => dimensions_with_shape(v.shape()),
             Self::F32(v) => dimensions_with_shape(v.shape()),
+            Self::Bool(v) => dimensions_with_shape(v.shape()),
+            Self::String(v) => dimensions_with_shape(v.shape()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::element::TensorElement;
+
+    fn dynamic<T>(
+        array: ndarray::ArrayD<T>,
+        variant: fn(Array<T, ndarray::IxDyn>) -> DynamicTensorData,
+    ) -> DynamicTensorData {
+        variant(Array(array.into_shared()))
+    }
+
+    fn values<T: TensorElement>(data: DynamicTensorData) -> Vec<T> {
+        T::try_from_dynamic(data).unwrap().into_iter().collect()
+    }
+
+    #[test]
+    fn test_concatenate() {
+        let a = dynamic(
+            ndarray::array![[1.0f32, 2.0]].into_dyn(),
+            DynamicTensorData::F32,
+        );
+        let b = dynamic(
+            ndarray::array![[3.0f32, 4.0], [5.0, 6.0]].into_dyn(),
+            DynamicTensorData::F32,
+        );
+
+        let data = DynamicTensorData::concatenate(&[&a, &b]).unwrap();
+        let expected = dynamic(
+            ndarray::array![[1.0f32, 2.0], [3.0, 4.0], [5.0, 6.0]].into_dyn(),
+            DynamicTensorData::F32,
+        );
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_concatenate_mismatched() {
+        let a = dynamic(
+            ndarray::array![[1.0f32, 2.0]].into_dyn(),
+            DynamicTensorData::F32,
+        );
+        let b = dynamic(
+            ndarray::array![[3i64, 4]].into_dyn(),
+            DynamicTensorData::I64,
+        );
+        let c = dynamic(
+            ndarray::array![[3.0f32, 4.0, 5.0]].into_dyn(),
+            DynamicTensorData::F32,
+        );
+
+        assert!(DynamicTensorData::concatenate(&[&a, &b]).is_err());
+        assert!(DynamicTensorData::concatenate(&[&a, &c]).is_err());
+        assert!(DynamicTensorData::concatenate(&[]).is_err());
+    }
+
+    #[test]
+    fn test_split() {
+        let data = dynamic(
+            ndarray::array![[1i64, 2], [3, 4], [5, 6]].into_dyn(),
+            DynamicTensorData::I64,
+        );
+
+        let splits = data.split(&[1, 2]).unwrap();
+        assert_eq!(splits.len(), 2);
+        assert_eq!(splits[0].shape(), [1, 2]);
+        assert_eq!(
+            splits[1],
+            dynamic(
+                ndarray::array![[3i64, 4], [5, 6]].into_dyn(),
+                DynamicTensorData::I64,
+            ),
+        );
+
+        // the splits are concatenated back into the original
+        let splits: Vec<_> = splits.iter().collect();
+        assert_eq!(DynamicTensorData::concatenate(&splits).unwrap(), data);
+
+        assert!(data.split(&[1, 1]).is_err());
+    }
+
+    #[test]
+    fn test_cast_round_trip() {
+        let data = dynamic(
+            ndarray::array![0u8, 128, 255].into_dyn(),
+            DynamicTensorData::U8,
+        );
+
+        let casted = data.cast(TensorType::F32).unwrap();
+        assert_eq!(values::<f32>(casted.clone()), [0.0, 128.0, 255.0]);
+        assert_eq!(casted.cast(TensorType::U8).unwrap(), data);
+
+        // the exactly representable values survive the half precision
+        let data = dynamic(
+            ndarray::array![0.5f32, -2.0, 1024.0].into_dyn(),
+            DynamicTensorData::F32,
+        );
+        let casted = DynamicTensorData::F16(data.to_f16().unwrap());
+        assert_eq!(casted.to_f32().unwrap().0, data.to_f32().unwrap().0);
+    }
+
+    #[test]
+    fn test_cast_saturation() {
+        let data = dynamic(
+            ndarray::array![-1.5f32, 300.0, f32::NAN, -1e10].into_dyn(),
+            DynamicTensorData::F32,
+        );
+
+        assert_eq!(
+            values::<u8>(data.cast(TensorType::U8).unwrap()),
+            [0, 255, 0, 0]
+        );
+        assert_eq!(
+            values::<i8>(data.cast(TensorType::I8).unwrap()),
+            [-1, 127, 0, -128]
+        );
+        assert_eq!(
+            values::<bool>(data.cast(TensorType::Bool).unwrap()),
+            [true, true, true, true]
+        );
+
+        let data = dynamic(
+            ndarray::array![i64::MAX, i64::MIN, 7].into_dyn(),
+            DynamicTensorData::I64,
+        );
+        assert_eq!(
+            values::<i32>(data.cast(TensorType::I32).unwrap()),
+            [i32::MAX, i32::MIN, 7]
+        );
+    }
+
+    #[test]
+    fn test_cast_strings() {
+        let data = dynamic(
+            ndarray::array!["a".to_string()].into_dyn(),
+            DynamicTensorData::String,
+        );
+        assert!(data.cast(TensorType::F32).is_err());
+        assert_eq!(data.cast(TensorType::String).unwrap(), data);
+
+        let data = dynamic(ndarray::array![1.0f32].into_dyn(), DynamicTensorData::F32);
+        assert!(data.cast(TensorType::String).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let array = ndarray::array![[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0]].into_dyn();
+        assert!(dynamic(array.clone(), DynamicTensorData::F32)
+            .validate()
+            .is_ok());
+
+        // the transposed arrays are contiguous, but not in the row-major order
+        assert!(dynamic(array.reversed_axes(), DynamicTensorData::F32)
+            .validate()
+            .is_err());
+
+        assert!(
+            dynamic(ndarray::arr0(1.0f32).into_dyn(), DynamicTensorData::F32)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            dynamic(ndarray::ArrayD::zeros(vec![0, 3]), DynamicTensorData::F32)
+                .validate()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_validate_sliced() {
+        let array = ndarray::array![[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0]].into_dyn();
+
+        // the sliced arrays are contiguous, but hold the whole buffer of their origins
+        let sliced = array.slice_move(ndarray::s![..1, ..]);
+        assert!(sliced.is_standard_layout());
+        let error = dynamic(sliced.clone(), DynamicTensorData::F32)
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "number of the elements mismatched: expected 3, but given 6",
+        );
+
+        // the copied ones are accepted
+        let data = dynamic(sliced.to_owned(), DynamicTensorData::F32)
+            .validate()
+            .unwrap();
+        assert_eq!(values::<f32>(data), [1.0, 2.0, 3.0]);
+    }
+}
@@ -0,0 +1,171 @@
+use ipis::core::{
+    anyhow::{bail, Result},
+    ndarray,
+    value::array::Array,
+};
+
+use super::{
+    dynamic::DynamicTensorData, shape::Shape, ty::TensorType, AsTensorData, Tensor, ToTensor,
+};
+use crate::error::IpnisError;
+
+/// The element type of the dynamic tensors.
+pub trait TensorElement: Sized {
+    const TYPE: TensorType;
+
+    fn try_from_dynamic(data: DynamicTensorData) -> Result<ndarray::ArrayD<Self>>;
+
+    fn into_dynamic(array: ndarray::ArrayD<Self>) -> DynamicTensorData;
+}
+
+fn type_mismatched<T>(expected: TensorType, data: &DynamicTensorData) -> Result<T> {
+    let given = data.ty();
+    bail!(IpnisError::OutputDtypeMismatch { expected, given })
+}
+
+impl TensorElement for i32 {
+    const TYPE: TensorType = TensorType::I32;
+
+    fn try_from_dynamic(data: DynamicTensorData) -> Result<ndarray::ArrayD<Self>> {
+        match data {
+            DynamicTensorData::I32(v) => Ok(v.0.into_owned()),
+            _ => type_mismatched(Self::TYPE, &data),
+        }
+    }
+
+    fn into_dynamic(array: ndarray::ArrayD<Self>) -> DynamicTensorData {
+        DynamicTensorData::I32(Array(array.into_shared()))
+    }
+}
+
+impl TensorElement for i64 {
+    const TYPE: TensorType = TensorType::I64;
+
+    fn try_from_dynamic(data: DynamicTensorData) -> Result<ndarray::ArrayD<Self>> {
+        match data {
+            DynamicTensorData::I64(v) => Ok(v.0.into_owned()),
+            _ => type_mismatched(Self::TYPE, &data),
+        }
+    }
+
+    fn into_dynamic(array: ndarray::ArrayD<Self>) -> DynamicTensorData {
+        DynamicTensorData::I64(Array(array.into_shared()))
+    }
+}
+
+impl TensorElement for i8 {
+    const TYPE: TensorType = TensorType::I8;
+
+    fn try_from_dynamic(data: DynamicTensorData) -> Result<ndarray::ArrayD<Self>> {
+        match data {
+            DynamicTensorData::I8(v) => Ok(v.0.into_owned()),
+            _ => type_mismatched(Self::TYPE, &data),
+        }
+    }
+
+    fn into_dynamic(array: ndarray::ArrayD<Self>) -> DynamicTensorData {
+        DynamicTensorData::I8(Array(array.into_shared()))
+    }
+}
+
+impl TensorElement for u8 {
+    const TYPE: TensorType = TensorType::U8;
+
+    fn try_from_dynamic(data: DynamicTensorData) -> Result<ndarray::ArrayD<Self>> {
+        match data {
+            DynamicTensorData::U8(v) => Ok(v.0.into_owned()),
+            _ => type_mismatched(Self::TYPE, &data),
+        }
+    }
+
+    fn into_dynamic(array: ndarray::ArrayD<Self>) -> DynamicTensorData {
+        DynamicTensorData::U8(Array(array.into_shared()))
+    }
+}
+
+impl TensorElement for ::half::f16 {
+    const TYPE: TensorType = TensorType::F16;
+
+    fn try_from_dynamic(data: DynamicTensorData) -> Result<ndarray::ArrayD<Self>> {
+        match data {
+            DynamicTensorData::F16(v) => Ok(v.mapv(::half::f16::from_bits)),
+            _ => type_mismatched(Self::TYPE, &data),
+        }
+    }
+
+    fn into_dynamic(array: ndarray::ArrayD<Self>) -> DynamicTensorData {
+        DynamicTensorData::F16(Array(array.mapv(::half::f16::to_bits).into_shared()))
+    }
+}
+
+impl TensorElement for f32 {
+    const TYPE: TensorType = TensorType::F32;
+
+    fn try_from_dynamic(data: DynamicTensorData) -> Result<ndarray::ArrayD<Self>> {
+        match data {
+            DynamicTensorData::F32(v) => Ok(v.0.into_owned()),
+            _ => type_mismatched(Self::TYPE, &data),
+        }
+    }
+
+    fn into_dynamic(array: ndarray::ArrayD<Self>) -> DynamicTensorData {
+        DynamicTensorData::F32(Array(array.into_shared()))
+    }
+}
+
+impl TensorElement for bool {
+    const TYPE: TensorType = TensorType::Bool;
+
+    fn try_from_dynamic(data: DynamicTensorData) -> Result<ndarray::ArrayD<Self>> {
+        match data {
+            DynamicTensorData::Bool(v) => Ok(v.0.into_owned()),
+            _ => type_mismatched(Self::TYPE, &data),
+        }
+    }
+
+    fn into_dynamic(array: ndarray::ArrayD<Self>) -> DynamicTensorData {
+        DynamicTensorData::Bool(Array(array.into_shared()))
+    }
+}
+
+impl TensorElement for String {
+    const TYPE: TensorType = TensorType::String;
+
+    fn try_from_dynamic(data: DynamicTensorData) -> Result<ndarray::ArrayD<Self>> {
+        match data {
+            DynamicTensorData::String(v) => Ok(v.0.into_owned()),
+            _ => type_mismatched(Self::TYPE, &data),
+        }
+    }
+
+    fn into_dynamic(array: ndarray::ArrayD<Self>) -> DynamicTensorData {
+        DynamicTensorData::String(Array(array.into_shared()))
+    }
+}
+
+impl<N, T> From<(N, ndarray::ArrayD<T>)> for Tensor
+where
+    N: Into<String>,
+    T: TensorElement,
+{
+    fn from((name, array): (N, ndarray::ArrayD<T>)) -> Self {
+        Self {
+            name: name.into(),
+            data: T::into_dynamic(array).into(),
+        }
+    }
+}
+
+/// Binds the array as it is, such as the int64 token ids of the transformers.
+///
+/// The dynamic axes of the shape are matched with anything.
+impl<T> ToTensor for ndarray::ArrayD<T>
+where
+    T: TensorElement + Clone,
+{
+    fn to_tensor(&self, shape: &Shape) -> Result<Tensor> {
+        let tensor = Tensor::from((shape.name.clone(), self.clone()));
+        shape.validate(&tensor.shape())?;
+        Ok(tensor)
+    }
+}
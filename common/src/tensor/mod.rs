@@ -1,12 +1,16 @@
+pub mod batch;
 pub mod class;
 pub mod dimension;
 pub mod dynamic;
+pub mod element;
+pub mod ops;
+pub mod safetensors;
 pub mod shape;
 pub mod ty;
 
 use bytecheck::CheckBytes;
 use ipis::core::{
-    anyhow::{bail, Result},
+    anyhow::{anyhow, bail, Result},
     signed::IsSigned,
 };
 #[cfg(feature = "onnxruntime")]
@@ -66,6 +70,25 @@ impl ToTensor for Tensor {
     }
 }
 
+impl Tensor {
+    /// Checks whether the elements of the dynamic tensor match its shape.
+    pub fn validate(self) -> Result<Self> {
+        match self.data {
+            TensorData::Dynamic(data) => {
+                let name = self.name;
+                let data = data
+                    .validate()
+                    .map_err(|error| anyhow!("invalid tensor: {name}: {error}"))?;
+                Ok(Self {
+                    name,
+                    data: TensorData::Dynamic(data),
+                })
+            }
+            _ => Ok(self),
+        }
+    }
+}
+
 impl<Data> Tensor<Data>
 where
     Data: AsTensorData,
@@ -86,6 +109,7 @@ where
             name: self.name.as_str().into(),
             ty: self.data.ty(),
             dimensions: self.data.dimensions(),
+            symbolic_dimensions: Default::default(),
         }
     }
 }
@@ -156,6 +180,7 @@ impl TensorData {
             name: name.to_string(),
             ty: self.ty(),
             dimensions: self.dimensions(),
+            symbolic_dimensions: Default::default(),
         }
     }
 }
@@ -0,0 +1,395 @@
+use std::collections::BTreeMap;
+
+use ipis::core::{
+    anyhow::{anyhow, bail, Result},
+    ndarray,
+    value::array::Array,
+};
+
+use super::{class::ClassTensorData, dynamic::DynamicTensorData, AsTensorData, Tensor, TensorData};
+
+/// Returns the index of the largest value of each sample.
+///
+/// The tensor should be a float tensor of `[batch, classes]` or `[classes]`.
+pub fn argmax(tensor: &Tensor) -> Result<Vec<usize>> {
+    let name = &tensor.name;
+
+    to_logits(tensor)?
+        .outer_iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(index, _)| index)
+                .ok_or_else(|| anyhow!("empty tensor: {name}"))
+        })
+        .collect()
+}
+
+/// Returns the `k` largest values of each sample with their indices, in descending order.
+///
+/// The tensor should be a float tensor of `[batch, classes]` or `[classes]`.
+pub fn top_k(tensor: &Tensor, k: usize) -> Result<Vec<Vec<(usize, f32)>>> {
+    Ok(to_logits(tensor)?
+        .outer_iter()
+        .map(|row| {
+            let mut row: Vec<_> = row.iter().copied().enumerate().collect();
+            row.sort_unstable_by(|(_, a), (_, b)| b.total_cmp(a));
+            row.truncate(k);
+            row
+        })
+        .collect())
+}
+
+/// Returns the `k` largest values of each sample with their labels, in descending order.
+///
+/// The tensor should be a float tensor of `[batch, classes]` or `[classes]`,
+/// having the same number of the classes with the labels.
+pub fn decode_labels(
+    tensor: &Tensor,
+    labels: &[String],
+    k: usize,
+) -> Result<Vec<Vec<(String, f32)>>> {
+    let name = &tensor.name;
+
+    let classes = to_logits(tensor)?.ncols();
+    let num_labels = labels.len();
+    if classes != num_labels {
+        bail!(
+            "number of the labels mismatched: {name}: expected {classes}, but given {num_labels}"
+        );
+    }
+
+    Ok(top_k(tensor, k)?
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|(index, value)| (labels[index].clone(), value))
+                .collect()
+        })
+        .collect())
+}
+
+/// Parses the labels of the classes in order.
+///
+/// The labels can be either newline-delimited, a JSON array of strings,
+/// or a JSON object mapping the indices to the labels, such as `{"0": "cat"}`.
+pub fn parse_labels(text: &str) -> Result<Vec<String>> {
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('[') {
+        Ok(::serde_json::from_str(trimmed)?)
+    } else if trimmed.starts_with('{') {
+        let labels: BTreeMap<String, String> = ::serde_json::from_str(trimmed)?;
+        let labels = labels
+            .into_iter()
+            .map(|(index, label)| Ok((index.parse::<usize>()?, label)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        // validate indices
+        labels
+            .into_iter()
+            .enumerate()
+            .map(|(expected, (given, label))| {
+                if expected == given {
+                    Ok(label)
+                } else {
+                    bail!("missing label: expected index {expected}, but given {given}")
+                }
+            })
+            .collect()
+    } else {
+        Ok(text
+            .lines()
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map(Into::into)
+            .collect())
+    }
+}
+
+/// Loads the labels file, as described in [`parse_labels`].
+pub fn load_labels(path: impl AsRef<::std::path::Path>) -> Result<Vec<String>> {
+    parse_labels(&::std::fs::read_to_string(path)?)
+}
+
+/// Converts the logits into the probabilities along the given axis.
+///
+/// The maximum of each lane is subtracted before the exponentiation,
+/// so that the large logits do not overflow.
+pub fn softmax(tensor: &Tensor, axis: usize) -> Result<Tensor> {
+    fn softmax_array(
+        array: ndarray::ArrayViewD<'_, f32>,
+        axis: usize,
+    ) -> Result<ndarray::ArrayD<f32>> {
+        let rank = array.ndim();
+        if axis >= rank {
+            bail!("axis out of bounds: expected less than {rank}, but given {axis}");
+        }
+
+        let mut output = array.to_owned();
+        for mut lane in output.lanes_mut(ndarray::Axis(axis)) {
+            let max = lane.fold(f32::NEG_INFINITY, |max, &e| max.max(e));
+            lane.mapv_inplace(|e| (e - max).exp());
+
+            let sum = lane.sum();
+            lane.mapv_inplace(|e| e / sum);
+        }
+        Ok(output)
+    }
+
+    let name = &tensor.name;
+
+    let data = match &tensor.data {
+        TensorData::Dynamic(DynamicTensorData::F32(v)) => {
+            DynamicTensorData::F32(Array(softmax_array(v.view(), axis)?.into_shared())).into()
+        }
+        TensorData::Class(ClassTensorData::F32(v)) => ClassTensorData::F32(Array(
+            softmax_array(v.view().into_dyn(), axis)?
+                .into_dimensionality()?
+                .into_shared(),
+        ))
+        .into(),
+        _ => {
+            let ty = tensor.ty();
+            bail!("tensor type mismatched: {name}: expected F32, but given {ty:?}")
+        }
+    };
+
+    Ok(Tensor {
+        name: name.clone(),
+        data,
+    })
+}
+
+/// Subtracts the mean and divides by the deviation of each channel along the given axis.
+pub fn normalize(tensor: &Tensor, mean: &[f32], std: &[f32], axis: usize) -> Result<Tensor> {
+    let name = &tensor.name;
+
+    let array = match &tensor.data {
+        TensorData::Dynamic(DynamicTensorData::F32(v)) => v,
+        _ => {
+            let ty = tensor.ty();
+            bail!("tensor type mismatched: {name}: expected F32, but given {ty:?}")
+        }
+    };
+
+    // validate parameters
+    let rank = array.ndim();
+    if axis >= rank {
+        bail!("axis out of bounds: {name}: expected less than {rank}, but given {axis}");
+    }
+    let channels = array.shape()[axis];
+    for (kind, params) in [("mean", mean), ("std", std)] {
+        let given = params.len();
+        if given != channels {
+            bail!(
+                "number of the {kind} mismatched: {name}: expected {channels}, but given {given}"
+            );
+        }
+    }
+    if std.contains(&0.0) {
+        bail!("zero std: {name}: {std:?}")
+    }
+
+    let mut output = array.to_owned();
+    for (index, mut channel) in output.axis_iter_mut(ndarray::Axis(axis)).enumerate() {
+        let (mean, std) = (mean[index], std[index]);
+        channel.mapv_inplace(|e| (e - mean) / std);
+    }
+
+    Ok(Tensor {
+        name: name.clone(),
+        data: DynamicTensorData::F32(Array(output.into_shared())).into(),
+    })
+}
+
+/// Borrows the float tensor as `[batch, classes]`, without copying.
+fn to_logits(tensor: &Tensor) -> Result<ndarray::ArrayView2<'_, f32>> {
+    let name = &tensor.name;
+
+    let logits = match &tensor.data {
+        TensorData::Dynamic(DynamicTensorData::F32(v)) => v.view(),
+        TensorData::Class(ClassTensorData::F32(v)) => v.view().into_dyn(),
+        _ => {
+            let ty = tensor.ty();
+            bail!("tensor type mismatched: {name}: expected F32, but given {ty:?}")
+        }
+    };
+
+    match logits.ndim() {
+        1 => Ok(logits.insert_axis(ndarray::Axis(0)).into_dimensionality()?),
+        2 => Ok(logits.into_dimensionality()?),
+        rank => bail!("unsupported tensor rank: {name}: expected 1 or 2, but given {rank}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::element::TensorElement;
+
+    fn tensor(name: &str, array: ndarray::ArrayD<f32>) -> Tensor {
+        Tensor {
+            name: name.into(),
+            data: DynamicTensorData::F32(Array(array.into_shared())).into(),
+        }
+    }
+
+    fn to_values(tensor: Tensor) -> ndarray::ArrayD<f32> {
+        match tensor.data {
+            TensorData::Dynamic(data) => f32::try_from_dynamic(data).unwrap(),
+            _ => unreachable!("expected a dynamic tensor"),
+        }
+    }
+
+    fn assert_close(given: &ndarray::ArrayD<f32>, expected: &[f32]) {
+        assert_eq!(given.len(), expected.len());
+        for (given, expected) in given.iter().zip(expected) {
+            assert!(
+                (given - expected).abs() < 1e-5,
+                "expected {expected}, but given {given}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_argmax() {
+        let logits = tensor(
+            "logits",
+            ndarray::array![[0.1f32, 0.7, 0.2], [0.5, 0.3, 0.2]].into_dyn(),
+        );
+        assert_eq!(argmax(&logits).unwrap(), [1, 0]);
+
+        // the single sample is treated as a batch of one
+        let logits = tensor("logits", ndarray::array![0.1f32, 0.2, 0.7].into_dyn());
+        assert_eq!(argmax(&logits).unwrap(), [2]);
+    }
+
+    #[test]
+    fn test_top_k() {
+        let logits = tensor(
+            "logits",
+            ndarray::array![[0.1f32, 0.7, 0.2], [0.5, 0.3, 0.2]].into_dyn(),
+        );
+        assert_eq!(
+            top_k(&logits, 2).unwrap(),
+            [vec![(1, 0.7), (2, 0.2)], vec![(0, 0.5), (1, 0.3)]],
+        );
+
+        // `k` larger than the classes returns all of them
+        assert_eq!(top_k(&logits, 4).unwrap()[0].len(), 3);
+    }
+
+    #[test]
+    fn test_top_k_unsupported_rank() {
+        let logits = tensor("logits", ndarray::ArrayD::zeros(vec![1, 2, 3]));
+        assert!(top_k(&logits, 1).is_err());
+    }
+
+    #[test]
+    fn test_softmax() {
+        let logits = tensor("logits", ndarray::array![1.0f32, 2.0, 3.0].into_dyn());
+
+        let output = softmax(&logits, 0).unwrap();
+        assert_eq!(output.name, "logits");
+        assert_close(&to_values(output), &[0.090031, 0.244728, 0.665241]);
+    }
+
+    #[test]
+    fn test_softmax_large_magnitudes() {
+        let logits = tensor(
+            "logits",
+            ndarray::array![[1000.0f32, 1001.0, 1002.0], [-1000.0, 0.0, 1000.0]].into_dyn(),
+        );
+
+        let output = to_values(softmax(&logits, 1).unwrap());
+        assert!(output.iter().all(|e| e.is_finite()));
+        assert_close(&output, &[0.090031, 0.244728, 0.665241, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_softmax_axis() {
+        let logits = tensor(
+            "logits",
+            ndarray::array![[1.0f32, 5.0], [2.0, 5.0], [3.0, 5.0]].into_dyn(),
+        );
+
+        let output = to_values(softmax(&logits, 0).unwrap());
+        assert_close(
+            &output,
+            &[
+                0.090031,
+                1.0 / 3.0,
+                0.244728,
+                1.0 / 3.0,
+                0.665241,
+                1.0 / 3.0,
+            ],
+        );
+
+        assert!(softmax(&logits, 2).is_err());
+    }
+
+    #[test]
+    fn test_softmax_type_mismatched() {
+        let ids = Tensor {
+            name: "ids".into(),
+            data: DynamicTensorData::I64(Array(
+                ndarray::array![1i64, 2, 3].into_dyn().into_shared(),
+            ))
+            .into(),
+        };
+        assert!(softmax(&ids, 0).is_err());
+    }
+
+    #[test]
+    fn test_parse_labels() {
+        let expected = ["cat", "dog"];
+        assert_eq!(parse_labels("cat\n\n dog \n").unwrap(), expected);
+        assert_eq!(parse_labels(r#"["cat", "dog"]"#).unwrap(), expected);
+        assert_eq!(
+            parse_labels(r#" {"1": "dog", "0": "cat"}"#).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parse_labels_missing_index() {
+        assert!(parse_labels(r#"{"0": "cat", "2": "dog"}"#).is_err());
+        assert!(parse_labels(r#"{"cat": "dog"}"#).is_err());
+    }
+
+    #[test]
+    fn test_decode_labels() {
+        let labels = parse_labels("cat\ndog\nbird").unwrap();
+        let logits = tensor("logits", ndarray::array![0.1f32, 0.7, 0.2].into_dyn());
+        assert_eq!(
+            decode_labels(&logits, &labels, 2).unwrap(),
+            [vec![("dog".to_string(), 0.7), ("bird".to_string(), 0.2)]],
+        );
+
+        assert!(decode_labels(&logits, &labels[..2], 2).is_err());
+    }
+
+    #[test]
+    fn test_normalize() {
+        // [batch, channels, pixels]
+        let image = tensor(
+            "image",
+            ndarray::array![[[1.0f32, 3.0], [10.0, 20.0]]].into_dyn(),
+        );
+
+        let output = normalize(&image, &[1.0, 10.0], &[2.0, 5.0], 1).unwrap();
+        assert_eq!(output.name, "image");
+        assert_close(&to_values(output), &[0.0, 1.0, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn test_normalize_invalid_parameters() {
+        let image = tensor("image", ndarray::ArrayD::zeros(vec![1, 2, 2]));
+
+        assert!(normalize(&image, &[0.0], &[1.0], 1).is_err());
+        assert!(normalize(&image, &[0.0, 0.0], &[1.0, 0.0], 1).is_err());
+        assert!(normalize(&image, &[0.0, 0.0], &[1.0, 1.0], 3).is_err());
+    }
+}
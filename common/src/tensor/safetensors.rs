@@ -0,0 +1,242 @@
+use ipis::core::{
+    anyhow::{anyhow, bail, Result},
+    ndarray,
+    value::array::Array,
+};
+use serde_json::{json, Map, Value};
+
+use super::{dynamic::DynamicTensorData, Tensor, TensorData};
+
+/// Serializes the dynamic tensors in the safetensors format, keeping their order.
+///
+/// The strings cannot be serialized, as the format only supports the numbers.
+pub fn to_safetensors(tensors: &[Tensor]) -> Result<Vec<u8>> {
+    fn encode<T, const N: usize>(
+        array: &Array<T, ndarray::IxDyn>,
+        data: &mut Vec<u8>,
+        f: impl Fn(&T) -> [u8; N],
+    ) {
+        // NOTE: the elements are iterated in the logical (row-major) order
+        for e in array.iter() {
+            data.extend_from_slice(&f(e));
+        }
+    }
+
+    let mut header = Map::new();
+    let mut data = Vec::new();
+    for tensor in tensors {
+        let name = &tensor.name;
+        if header.contains_key(name) {
+            bail!("duplicated tensor: {name}")
+        }
+        let dynamic = match &tensor.data {
+            TensorData::Dynamic(v) => v,
+            _ => bail!("unsupported tensor: {name}: expected a dynamic tensor"),
+        };
+
+        let begin = data.len();
+        let dtype = match dynamic {
+            DynamicTensorData::I32(v) => {
+                encode(v, &mut data, |e| e.to_le_bytes());
+                "I32"
+            }
+            DynamicTensorData::I64(v) => {
+                encode(v, &mut data, |e| e.to_le_bytes());
+                "I64"
+            }
+            DynamicTensorData::I8(v) => {
+                encode(v, &mut data, |e| e.to_le_bytes());
+                "I8"
+            }
+            DynamicTensorData::U8(v) => {
+                encode(v, &mut data, |e| e.to_le_bytes());
+                "U8"
+            }
+            DynamicTensorData::F16(v) => {
+                encode(v, &mut data, |e| e.to_le_bytes());
+                "F16"
+            }
+            DynamicTensorData::F32(v) => {
+                encode(v, &mut data, |e| e.to_le_bytes());
+                "F32"
+            }
+            DynamicTensorData::Bool(v) => {
+                encode(v, &mut data, |e| [u8::from(*e)]);
+                "BOOL"
+            }
+            DynamicTensorData::String(_) => {
+                bail!("unsupported tensor type: {name}: String")
+            }
+        };
+        header.insert(
+            name.clone(),
+            json!({
+                "dtype": dtype,
+                "shape": dynamic.shape(),
+                "data_offsets": [begin, data.len()],
+            }),
+        );
+    }
+
+    // align the data into 8 bytes, padding the header with spaces
+    let mut header = ::serde_json::to_vec(&header)?;
+    while header.len() % 8 != 0 {
+        header.push(b' ');
+    }
+
+    let mut bytes = Vec::with_capacity(8 + header.len() + data.len());
+    bytes.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(&data);
+    Ok(bytes)
+}
+
+/// Deserializes the tensors in the safetensors format, in the order of their data.
+pub fn from_safetensors(bytes: &[u8]) -> Result<Vec<Tensor>> {
+    fn decode<T, const N: usize>(
+        data: &[u8],
+        shape: Vec<usize>,
+        f: impl Fn([u8; N]) -> T,
+    ) -> Result<Array<T, ndarray::IxDyn>> {
+        let expected = shape.iter().product::<usize>() * N;
+        let given = data.len();
+        if expected != given {
+            bail!("length of the tensor mismatched: expected {expected}, but given {given}");
+        }
+
+        let elements = data
+            .chunks_exact(N)
+            .map(|chunk| {
+                let mut buf = [0; N];
+                buf.copy_from_slice(chunk);
+                f(buf)
+            })
+            .collect();
+        Ok(Array(
+            ndarray::ArrayD::from_shape_vec(shape, elements)?.into_shared(),
+        ))
+    }
+
+    // parse header
+    let header_len = match bytes.get(..8) {
+        Some(len) => {
+            let mut buf = [0; 8];
+            buf.copy_from_slice(len);
+            u64::from_le_bytes(buf)
+        }
+        None => bail!("the safetensors header is truncated"),
+    };
+    let header_end = usize::try_from(header_len)
+        .ok()
+        .and_then(|len| len.checked_add(8))
+        .filter(|end| *end <= bytes.len())
+        .ok_or_else(|| anyhow!("the safetensors header is truncated"))?;
+    let header: Map<String, Value> = ::serde_json::from_slice(&bytes[8..header_end])?;
+    let data = &bytes[header_end..];
+
+    let mut tensors = header
+        .into_iter()
+        .filter(|(name, _)| name != "__metadata__")
+        .map(|(name, info)| {
+            let dtype = info["dtype"]
+                .as_str()
+                .ok_or_else(|| anyhow!("missing dtype: {name}"))?;
+            let shape = info["shape"]
+                .as_array()
+                .ok_or_else(|| anyhow!("missing shape: {name}"))?
+                .iter()
+                .map(|e| {
+                    e.as_u64()
+                        .and_then(|e| usize::try_from(e).ok())
+                        .ok_or_else(|| anyhow!("invalid shape: {name}"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let (begin, end) = match info["data_offsets"].as_array().map(Vec::as_slice) {
+                Some([begin, end]) => match (begin.as_u64(), end.as_u64()) {
+                    (Some(begin), Some(end)) => (begin as usize, end as usize),
+                    _ => bail!("invalid data offsets: {name}"),
+                },
+                _ => bail!("missing data offsets: {name}"),
+            };
+            let data = data
+                .get(begin..end)
+                .ok_or_else(|| anyhow!("data offsets out of bounds: {name}"))?;
+
+            let dynamic = match dtype {
+                "I32" => DynamicTensorData::I32(decode(data, shape, i32::from_le_bytes)?),
+                "I64" => DynamicTensorData::I64(decode(data, shape, i64::from_le_bytes)?),
+                "I8" => DynamicTensorData::I8(decode(data, shape, i8::from_le_bytes)?),
+                "U8" => DynamicTensorData::U8(decode(data, shape, u8::from_le_bytes)?),
+                "F16" => DynamicTensorData::F16(decode(data, shape, u16::from_le_bytes)?),
+                "F32" => DynamicTensorData::F32(decode(data, shape, f32::from_le_bytes)?),
+                "BOOL" => DynamicTensorData::Bool(decode(data, shape, |[e]: [u8; 1]| e != 0)?),
+                _ => bail!("unsupported tensor type: {name}: {dtype}"),
+            };
+            Ok((
+                begin,
+                Tensor {
+                    name,
+                    data: dynamic.into(),
+                },
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    tensors.sort_by_key(|(begin, _)| *begin);
+    Ok(tensors.into_iter().map(|(_, tensor)| tensor).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tensors() -> Vec<Tensor> {
+        vec![
+            Tensor::from((
+                "weight",
+                ndarray::array![[1.5f32, -2.0], [0.0, 4.25]].into_dyn(),
+            )),
+            Tensor::from(("ids", ndarray::array![i64::MIN, 0, i64::MAX].into_dyn())),
+            Tensor::from(("mask", ndarray::array![[true, false, true]].into_dyn())),
+            Tensor::from(("bias", ndarray::arr0(::half::f16::from_f32(0.5)).into_dyn())),
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let tensors = tensors();
+
+        let bytes = to_safetensors(&tensors).unwrap();
+        let header_len = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+        assert_eq!(header_len % 8, 0);
+
+        assert_eq!(from_safetensors(&bytes).unwrap(), tensors);
+    }
+
+    #[test]
+    fn test_duplicated_names() {
+        let mut tensors = tensors();
+        tensors.push(tensors[0].clone());
+
+        assert!(to_safetensors(&tensors).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_tensors() {
+        let tensors = [Tensor::from((
+            "text",
+            ndarray::array!["a".to_string()].into_dyn(),
+        ))];
+
+        assert!(to_safetensors(&tensors).is_err());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bytes = to_safetensors(&tensors()).unwrap();
+
+        assert!(from_safetensors(&bytes[..4]).is_err());
+        assert!(from_safetensors(&bytes[..16]).is_err());
+        assert!(from_safetensors(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
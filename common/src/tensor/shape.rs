@@ -1,5 +1,10 @@
+use std::collections::HashMap;
+
 use bytecheck::CheckBytes;
-use ipis::core::{anyhow::Result, signed::IsSigned};
+use ipis::core::{
+    anyhow::{bail, Result},
+    signed::IsSigned,
+};
 use rkyv::{Archive, Deserialize, Serialize};
 #[cfg(feature = "onnxruntime")]
 use {
@@ -15,6 +20,11 @@ pub struct Shape {
     pub name: String,
     pub(crate) ty: TensorType,
     pub(crate) dimensions: Dimensions,
+    /// The names of the dynamic axes (`dim_param`), as declared by the model.
+    ///
+    /// NOTE: it changes the archived layout of the shapes (and so the models),
+    ///       so the peers should be upgraded together
+    pub(crate) symbolic_dimensions: Vec<Option<String>>,
 }
 
 impl IsSigned for Shape {}
@@ -42,9 +52,38 @@ impl Shape {
                     _ => Dimensions::Unknown(dimensions),
                 }
             },
+            symbolic_dimensions: Default::default(),
         })
     }
 
+    pub fn with_symbolic_dimensions<I, S>(mut self, symbolic_dimensions: I) -> Self
+    where
+        I: IntoIterator<Item = Option<S>>,
+        S: ToString,
+    {
+        self.symbolic_dimensions = symbolic_dimensions
+            .into_iter()
+            .map(|e| e.map(|e| e.to_string()))
+            .collect();
+        self
+    }
+
+    pub fn ty(&self) -> TensorType {
+        self.ty
+    }
+
+    pub fn dimensions(&self) -> &Dimensions {
+        &self.dimensions
+    }
+
+    pub fn symbolic_dimensions(&self) -> &[Option<String>] {
+        &self.symbolic_dimensions
+    }
+
+    pub fn rank(&self) -> usize {
+        self.to_vec().len()
+    }
+
     pub fn contains(&self, child: &Self) -> bool {
         self.name == child.name
             && self.ty == child.ty
@@ -54,6 +93,50 @@ impl Shape {
     pub fn to_vec(&self) -> Vec<Option<usize>> {
         self.dimensions.to_vec()
     }
+
+    /// Replaces the dynamic axes with the concrete sizes of their symbolic names, if known.
+    pub fn resolve(&self, sizes: &HashMap<&str, usize>) -> Result<Self> {
+        let dimensions = self
+            .to_vec()
+            .into_iter()
+            .enumerate()
+            .map(|(index, dimension)| {
+                dimension.or_else(|| {
+                    self.symbolic_dimensions
+                        .get(index)?
+                        .as_deref()
+                        .and_then(|name| sizes.get(name).copied())
+                })
+            })
+            .collect();
+
+        Self::new(&self.name, self.ty, dimensions)
+            .map(|shape| shape.with_symbolic_dimensions(self.symbolic_dimensions.clone()))
+    }
+
+    /// Checks whether the given shape can be fed into this one.
+    /// The dynamic axes are matched with anything.
+    pub fn validate(&self, child: &Self) -> Result<()> {
+        let name = &self.name;
+
+        if self.ty != child.ty {
+            let expected = self.ty;
+            let given = child.ty;
+            bail!("tensor type mismatched: {name}: expected {expected:?}, but given {given:?}")
+        }
+
+        let expected = self.to_vec();
+        let given = child.to_vec();
+        if expected.len() != given.len()
+            || expected
+                .iter()
+                .zip(&given)
+                .any(|(expected, given)| expected.is_some() && expected != given)
+        {
+            bail!("shape mismatched: {name}: expected {expected:?}, but given {given:?}")
+        }
+        Ok(())
+    }
 }
 
 #[cfg(feature = "onnxruntime")]
@@ -64,6 +147,7 @@ impl TryFrom<&'_ Input> for Shape {
         let ty = value.input_type.try_into()?;
         let dimensions = value.dimensions().collect();
         Self::new(&value.name, ty, dimensions)
+            .map(|shape| shape.with_symbolic_dimensions(value.symbolic_dimensions()))
     }
 }
 
@@ -75,5 +159,6 @@ impl TryFrom<&'_ Output> for Shape {
         let ty = value.output_type.try_into()?;
         let dimensions = value.dimensions().collect();
         Self::new(&value.name, ty, dimensions)
+            .map(|shape| shape.with_symbolic_dimensions(value.symbolic_dimensions()))
     }
 }
@@ -7,6 +7,10 @@ use {
     onnxruntime::TensorElementDataType,
 };
 
+/// The element types of the tensors.
+///
+/// NOTE: the variants are archived by their order on the wire,
+///       so the new ones should be appended after the others
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Archive, Serialize, Deserialize)]
 #[archive(compare(PartialEq))]
 #[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, Hash))]
@@ -14,6 +18,11 @@ pub enum TensorType {
     I64,
     U8,
     F32,
+    I32,
+    F16,
+    String,
+    I8,
+    Bool,
 }
 
 impl IsSigned for TensorType {}
@@ -24,9 +33,14 @@ impl TryFrom<TensorElementDataType> for TensorType {
 
     fn try_from(value: TensorElementDataType) -> Result<Self, Self::Error> {
         match value {
+            TensorElementDataType::I32 => Ok(Self::I32),
             TensorElementDataType::I64 => Ok(Self::I64),
+            TensorElementDataType::I8 => Ok(Self::I8),
             TensorElementDataType::U8 => Ok(Self::U8),
+            TensorElementDataType::F16 => Ok(Self::F16),
             TensorElementDataType::F32 => Ok(Self::F32),
+            TensorElementDataType::Bool => Ok(Self::Bool),
+            TensorElementDataType::String => Ok(Self::String),
             _ => bail!("unsupported TensorType: {value:?}"),
         }
     }
@@ -36,9 +50,14 @@ impl TryFrom<TensorElementDataType> for TensorType {
 impl From<TensorType> for TensorElementDataType {
     fn from(value: TensorType) -> Self {
         match value {
+            TensorType::I32 => Self::I32,
             TensorType::I64 => Self::I64,
+            TensorType::I8 => Self::I8,
             TensorType::U8 => Self::U8,
+            TensorType::F16 => Self::F16,
             TensorType::F32 => Self::F32,
+            TensorType::Bool => Self::Bool,
+            TensorType::String => Self::String,
         }
     }
 }
@@ -1,2 +1,4 @@
 pub mod channel;
+#[cfg(feature = "image")]
+pub mod preprocess;
 pub mod tensor;
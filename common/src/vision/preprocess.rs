@@ -0,0 +1,200 @@
+use image::{imageops::FilterType, DynamicImage};
+use ipis::core::{anyhow::Result, ndarray, value::array::Array};
+
+use crate::tensor::{dynamic::DynamicTensorData, Tensor};
+
+/// The order of the axes of the image tensors.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ImageLayout {
+    /// `[batch, channels, height, width]`, as most of the PyTorch-exported models.
+    Nchw,
+    /// `[batch, height, width, channels]`, as most of the TensorFlow-exported models.
+    Nhwc,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreprocessOptions {
+    pub width: u32,
+    pub height: u32,
+    /// The mean of each RGB channel, subtracted after scaling the pixels into `[0, 1]`.
+    pub mean: [f32; 3],
+    /// The deviation of each RGB channel, dividing the pixels after subtracting the mean.
+    pub std: [f32; 3],
+    pub layout: ImageLayout,
+    /// Resizes the image keeping its aspect ratio and crops the center,
+    /// instead of stretching it into the target size.
+    pub center_crop: bool,
+}
+
+impl Default for PreprocessOptions {
+    /// The options of the ImageNet classifiers.
+    fn default() -> Self {
+        Self {
+            width: 224,
+            height: 224,
+            mean: [0.485, 0.456, 0.406],
+            std: [0.229, 0.224, 0.225],
+            layout: ImageLayout::Nchw,
+            center_crop: false,
+        }
+    }
+}
+
+/// Decodes the image and converts it into a normalized f32 RGB tensor with a single batch.
+pub fn preprocess_image(
+    name: impl Into<String>,
+    bytes: &[u8],
+    options: &PreprocessOptions,
+) -> Result<Tensor> {
+    const RESIZE_FILTER: FilterType = FilterType::Triangle;
+
+    // decode image
+    let image = ::image::load_from_memory(bytes)?;
+
+    // resize image
+    let PreprocessOptions {
+        width,
+        height,
+        mean,
+        std,
+        layout,
+        center_crop,
+    } = *options;
+    let image = if center_crop {
+        image.resize_to_fill(width, height, RESIZE_FILTER)
+    } else {
+        image.resize_exact(width, height, RESIZE_FILTER)
+    };
+    Ok(Tensor {
+        name: name.into(),
+        data: DynamicTensorData::F32(Array(normalize(&image, mean, std, layout).into_shared()))
+            .into(),
+    })
+}
+
+fn normalize(
+    image: &DynamicImage,
+    mean: [f32; 3],
+    std: [f32; 3],
+    layout: ImageLayout,
+) -> ndarray::ArrayD<f32> {
+    let image = image.to_rgb8();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+
+    let get_pixel = |c: usize, y: usize, x: usize| {
+        let value = image.get_pixel(x as u32, y as u32)[c] as f32 / 255.0;
+        (value - mean[c]) / std[c]
+    };
+
+    match layout {
+        ImageLayout::Nchw => {
+            ndarray::Array::from_shape_fn((1, 3, height, width), |(_, c, y, x)| get_pixel(c, y, x))
+                .into_dyn()
+        }
+        ImageLayout::Nhwc => {
+            ndarray::Array::from_shape_fn((1, height, width, 3), |(_, y, x, c)| get_pixel(c, y, x))
+                .into_dyn()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageOutputFormat, Rgb, RgbImage};
+
+    use super::*;
+    use crate::tensor::{element::TensorElement, TensorData};
+
+    /// The options mapping the pixels of `0` into `-1.0` and `255` into `1.0`.
+    const OPTIONS: PreprocessOptions = PreprocessOptions {
+        width: 4,
+        height: 2,
+        mean: [0.5; 3],
+        std: [0.5; 3],
+        layout: ImageLayout::Nchw,
+        center_crop: false,
+    };
+
+    const RED: Rgb<u8> = Rgb([255, 0, 51]);
+    const GREEN: Rgb<u8> = Rgb([0, 255, 0]);
+
+    fn encode_png(image: RgbImage) -> Vec<u8> {
+        let mut bytes = ::std::io::Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut bytes, ImageOutputFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    fn to_values(tensor: Tensor) -> ndarray::ArrayD<f32> {
+        assert_eq!(tensor.name, "image");
+        match tensor.data {
+            TensorData::Dynamic(data) => f32::try_from_dynamic(data).unwrap(),
+            _ => unreachable!("expected a dynamic tensor"),
+        }
+    }
+
+    fn assert_close(given: f32, expected: f32) {
+        assert!(
+            (given - expected).abs() < 1e-6,
+            "expected {expected}, but given {given}"
+        );
+    }
+
+    #[test]
+    fn test_preprocess_nchw() {
+        let bytes = encode_png(RgbImage::from_pixel(4, 2, RED));
+
+        let values = to_values(preprocess_image("image", &bytes, &OPTIONS).unwrap());
+        assert_eq!(values.shape(), [1, 3, 2, 4]);
+        assert_close(values[[0, 0, 1, 3]], 1.0);
+        assert_close(values[[0, 1, 1, 3]], -1.0);
+        assert_close(values[[0, 2, 1, 3]], -0.6);
+    }
+
+    #[test]
+    fn test_preprocess_nhwc() {
+        let bytes = encode_png(RgbImage::from_pixel(4, 2, RED));
+        let options = PreprocessOptions {
+            layout: ImageLayout::Nhwc,
+            ..OPTIONS
+        };
+
+        let values = to_values(preprocess_image("image", &bytes, &options).unwrap());
+        assert_eq!(values.shape(), [1, 2, 4, 3]);
+        assert_close(values[[0, 1, 3, 0]], 1.0);
+        assert_close(values[[0, 1, 3, 1]], -1.0);
+        assert_close(values[[0, 1, 3, 2]], -0.6);
+    }
+
+    #[test]
+    fn test_preprocess_center_crop() {
+        // the green center, between the red borders on the left and the right
+        let image = RgbImage::from_fn(16, 4, |x, _| if (4..12).contains(&x) { GREEN } else { RED });
+        let bytes = encode_png(image);
+        let options = PreprocessOptions {
+            width: 2,
+            height: 2,
+            center_crop: true,
+            ..OPTIONS
+        };
+
+        let values = to_values(preprocess_image("image", &bytes, &options).unwrap());
+        assert_eq!(values.shape(), [1, 3, 2, 2]);
+        for value in values.slice(ndarray::s![0, 0, .., ..]) {
+            assert_close(*value, -1.0);
+        }
+        for value in values.slice(ndarray::s![0, 1, .., ..]) {
+            assert_close(*value, 1.0);
+        }
+
+        // the borders are stretched into the image otherwise
+        let options = PreprocessOptions {
+            center_crop: false,
+            ..options
+        };
+        let values = to_values(preprocess_image("image", &bytes, &options).unwrap());
+        assert_eq!(values.shape(), [1, 3, 2, 2]);
+        assert!(values[[0, 0, 0, 0]] > -1.0);
+    }
+}
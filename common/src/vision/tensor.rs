@@ -57,10 +57,17 @@ impl AsTensorData for ImageTensorData {
 
     fn dimensions(&self) -> Dimensions {
         fn dimensions_with_shape(shape: &[usize]) -> Dimensions {
-            Dimensions::Image {
-                channels: shape[1].try_into().unwrap(),
-                width: Some(shape[2]),
-                height: Some(shape[3]),
+            // NOTE: the malformed images are described as they are, rather than panicking
+            match *shape {
+                [_, channels, width, height] => match channels.try_into() {
+                    Ok(channels) => Dimensions::Image {
+                        channels,
+                        width: Some(width),
+                        height: Some(height),
+                    },
+                    Err(_) => Dimensions::Unknown(shape.iter().map(|e| Some(*e)).collect()),
+                },
+                _ => Dimensions::Unknown(shape.iter().map(|e| Some(*e)).collect()),
             }
         }
 
@@ -98,10 +105,10 @@ impl ToTensor for DynamicImage {
         let ty = shape.ty;
         let get_image_shape = |c| (1, c, width, height);
         let data = match channels {
-            ImageChannel::L8 => convert_image(image.to_luma8(), ty, get_image_shape(1)),
-            ImageChannel::La8 => convert_image(image.to_luma_alpha8(), ty, get_image_shape(2)),
-            ImageChannel::Rgb8 => convert_image(image.to_rgb8(), ty, get_image_shape(3)),
-            ImageChannel::Rgba8 => convert_image(image.to_rgba8(), ty, get_image_shape(4)),
+            ImageChannel::L8 => convert_image(image.to_luma8(), ty, get_image_shape(1))?,
+            ImageChannel::La8 => convert_image(image.to_luma_alpha8(), ty, get_image_shape(2))?,
+            ImageChannel::Rgb8 => convert_image(image.to_rgb8(), ty, get_image_shape(3))?,
+            ImageChannel::Rgba8 => convert_image(image.to_rgba8(), ty, get_image_shape(4))?,
         };
 
         Ok(Tensor {
@@ -116,7 +123,7 @@ fn convert_image<I>(
     image: I,
     ty: TensorType,
     shape: (usize, usize, usize, usize),
-) -> ImageTensorData
+) -> anyhow::Result<ImageTensorData>
 where
     I: GenericImageView,
     <I as GenericImageView>::Pixel: Pixel<Subpixel = u8>,
@@ -128,12 +135,17 @@ where
     };
 
     match ty {
-        TensorType::U8 => ImageTensorData::U8(Array(
+        TensorType::U8 => Ok(ImageTensorData::U8(Array(
             ndarray::Array::from_shape_fn(shape, get_pixel).into(),
-        )),
-        TensorType::F32 => ImageTensorData::F32(Array(
+        ))),
+        TensorType::F32 => Ok(ImageTensorData::F32(Array(
             ndarray::Array::from_shape_fn(shape, |idx| (get_pixel(idx) as f32) / 255.0).into(),
-        )),
-        TensorType::I64 => unreachable!("unsupported TensorType: {:?}", ty),
+        ))),
+        TensorType::I64
+        | TensorType::I32
+        | TensorType::F16
+        | TensorType::String
+        | TensorType::I8
+        | TensorType::Bool => bail!("unsupported TensorType of the images: {ty:?}"),
     }
 }